@@ -1,16 +1,434 @@
-use std::process::Command;
+use std::io::{Read, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread::{self, JoinHandle};
 
 use anyhow::anyhow;
 use anyhow::Context;
 use anyhow::Result;
 
-use crate::file_operations::FileOperations;
+use crate::config;
+use crate::file_operations::{
+    FileEntry, FileInfo, FileKind, FileOperations, FileReader, FileWriter, TarReader, TarWriter,
+    Volume,
+};
+
+/// Quotes string for passing it to device shell
+pub fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Reads whole pipe in background thread, so process never blocks on full pipe
+fn collect_output<R: Read + Send + 'static>(mut pipe: R) -> JoinHandle<String> {
+    thread::spawn(move || {
+        let mut output = Vec::new();
+        let _ = pipe.read_to_end(&mut output);
+        String::from_utf8_lossy(&output).to_string()
+    })
+}
+
+/// Running adb process. Reading from it reads process stdout, writing into it writes process stdin
+pub struct AdbProcess {
+    child: Child,
+    stdout: Option<ChildStdout>,
+    stdin: Option<ChildStdin>,
+    stdout_output: Option<JoinHandle<String>>,
+    stderr_output: Option<JoinHandle<String>>,
+}
+
+impl AdbProcess {
+    fn spawn(mut command: Command, stdin: Stdio, stdout: Stdio) -> Result<Self> {
+        let mut child = command
+            .stdin(stdin)
+            .stdout(stdout)
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Failed to create adb process")?;
+
+        Ok(Self {
+            stdout: child.stdout.take(),
+            stdin: child.stdin.take(),
+            stdout_output: None,
+            stderr_output: child.stderr.take().map(collect_output),
+            child,
+        })
+    }
+
+    /// Collects process stdout in background instead of reading it, for processes that only report text
+    fn collect_stdout(mut self) -> Self {
+        self.stdout_output = self.stdout.take().map(collect_output);
+        self
+    }
+
+    /// Stops process without checking its result
+    pub fn kill(mut self) -> Result<()> {
+        self.child.kill()?;
+        self.child.wait()?;
+        Ok(())
+    }
+
+    /// Closes process stdin and waits for process exit.
+    /// Returns whether process succeeded and its collected stdout and stderr
+    fn wait_output(mut self) -> Result<(bool, String, String)> {
+        self.stdin.take();
+        self.stdout.take();
+        let status = self.child.wait()?;
+        let stdout = self
+            .stdout_output
+            .take()
+            .and_then(|output| output.join().ok())
+            .unwrap_or_default();
+        let stderr = self
+            .stderr_output
+            .take()
+            .and_then(|output| output.join().ok())
+            .unwrap_or_default();
+
+        Ok((status.success(), stdout, stderr))
+    }
+
+    /// Closes process stdin, waits for process exit and checks its exit status
+    pub fn finish(self) -> Result<()> {
+        let (success, _, stderr) = self.wait_output()?;
+        if !success {
+            return Err(anyhow!(stderr));
+        }
+        Ok(())
+    }
+
+    /// Waits for tar process and gets files it reported in verbose output
+    fn finish_tar(self) -> Result<Vec<String>> {
+        let (success, stdout, stderr) = self.wait_output()?;
+        tar_result(success, &format!("{}\n{}", stdout, stderr))
+    }
+}
+
+impl Drop for AdbProcess {
+    /// Process that was not finished, like on error, is killed so it does not keep running
+    fn drop(&mut self) {
+        if let Ok(None) = self.child.try_wait() {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+/// Gets files tar reported in its verbose `output`.
+/// Failed tar is only an error if it did not process any file, otherwise missing files are
+/// reported by caller
+fn tar_result(success: bool, output: &str) -> Result<Vec<String>> {
+    let files = parse_tar_verbose(output);
+
+    if !success {
+        log::error!("tar failed: {}", output);
+        if files.is_empty() {
+            return Err(anyhow!(output.trim().to_string()));
+        }
+    }
+    Ok(files)
+}
+
+/// Gets file names from `tar -v` output, skipping tar messages
+fn parse_tar_verbose(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with("tar:"))
+        .map(|line| {
+            line.trim_start_matches("./")
+                .trim_end_matches('/')
+                .to_string()
+        })
+        .filter(|line| !line.is_empty() && line != ".")
+        .collect()
+}
+
+impl Read for AdbProcess {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match &mut self.stdout {
+            Some(stdout) => stdout.read(buf),
+            None => Ok(0),
+        }
+    }
+}
+
+impl Write for AdbProcess {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match &mut self.stdin {
+            Some(stdin) => stdin.write(buf),
+            None => Ok(0),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match &mut self.stdin {
+            Some(stdin) => stdin.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl FileReader for AdbProcess {
+    fn finish(self: Box<Self>) -> Result<()> {
+        AdbProcess::finish(*self)
+    }
+
+    fn cancel(self: Box<Self>) -> Result<()> {
+        self.kill()
+    }
+}
+
+impl FileWriter for AdbProcess {
+    fn finish(self: Box<Self>) -> Result<()> {
+        AdbProcess::finish(*self)
+    }
+
+    fn cancel(self: Box<Self>) -> Result<()> {
+        self.kill()
+    }
+}
+
+impl TarReader for AdbProcess {
+    fn finish(self: Box<Self>) -> Result<Vec<String>> {
+        self.finish_tar()
+    }
+
+    fn cancel(self: Box<Self>) -> Result<()> {
+        self.kill()
+    }
+}
+
+impl TarWriter for AdbProcess {
+    fn finish(self: Box<Self>) -> Result<Vec<String>> {
+        self.finish_tar()
+    }
+
+    fn cancel(self: Box<Self>) -> Result<()> {
+        self.kill()
+    }
+}
+
+/// Directory on device writable by shell user, where files are staged before `su` copies them
+const SU_STAGING_DIRECTORY: &str = "/data/local/tmp";
+
+/// How long to wait for device command to finish after its upload was closed, in tenths of second
+const REPORT_WAIT: u32 = 100;
+
+/// Separates directory listing from list of broken symlinks in output of `get_entries` command
+const BROKEN_LINKS_MARKER: &str = "--adbexplorer-broken-links--";
+
+/// Separates list of broken symlinks from list of symlinks to directories in output of
+/// `get_entries` command
+const DIRECTORY_LINKS_MARKER: &str = "--adbexplorer-directory-links--";
+
+/// User that device commands run as
+#[derive(Clone, Debug, PartialEq)]
+pub enum Privilege {
+    /// Unprivileged shell user
+    Shell,
+    /// Commands are wrapped into `su -c`
+    Su,
+    /// adbd is restarted as root with `adb root`
+    AdbRoot,
+    /// Commands run as debuggable app user with `run-as <package>`
+    RunAs(String),
+}
+
+impl Privilege {
+    fn next(&self) -> Self {
+        match self {
+            Privilege::Shell => Privilege::Su,
+            Privilege::Su => Privilege::AdbRoot,
+            Privilege::AdbRoot | Privilege::RunAs(_) => Privilege::Shell,
+        }
+    }
+}
+
+/// Device command run with `exec-in` or `exec-out`, which pass binary data unchanged unlike `shell`,
+/// but do not report exit status and mix stderr into data. Command saves its messages and exit
+/// status into report files, which are checked after transfer
+struct ExecProcess {
+    process: AdbProcess,
+    device: Device,
+    report: String,
+}
+
+impl ExecProcess {
+    fn status_path(&self) -> String {
+        format!("{}.status", self.report)
+    }
+
+    /// Waits for transfer and for device command to finish.
+    /// Returns whether command succeeded and its messages
+    fn report(self) -> Result<(bool, String)> {
+        let status_path = self.status_path();
+        let (_, _, stderr) = self.process.wait_output()?;
+        let report = self.device.shell_as_user(&format!(
+            "i=0; while [ ! -s {1} ] && [ $i -lt {2} ]; do sleep 0.1; i=$((i+1)); done; \
+             cat {1} 2>/dev/null || echo; cat {0}; rm -f {0} {1}",
+            shell_quote(&self.report),
+            shell_quote(&status_path),
+            REPORT_WAIT
+        ))?;
+
+        let (status, output) = report.split_once('\n').unwrap_or((&report, ""));
+        match status.trim().parse::<i32>() {
+            Ok(status) => Ok((status == 0, output.to_string())),
+            Err(_) => Err(anyhow!(
+                "Transfer with device {} did not complete. {}",
+                self.device.name,
+                stderr.trim()
+            )),
+        }
+    }
+
+    /// Waits for transfer and checks that device command succeeded
+    fn finish_file(self) -> Result<()> {
+        let (success, output) = self.report()?;
+        if !success {
+            return Err(anyhow!(output.trim().to_string()));
+        }
+        Ok(())
+    }
+
+    fn abort(self) -> Result<()> {
+        let status_path = self.status_path();
+        self.process.kill()?;
+        self.device.shell_as_user(&format!(
+            "rm -f {} {}",
+            shell_quote(&self.report),
+            shell_quote(&status_path)
+        ))?;
+        Ok(())
+    }
+}
+
+impl Read for ExecProcess {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.process.read(buf)
+    }
+}
+
+impl Write for ExecProcess {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.process.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.process.flush()
+    }
+}
+
+impl FileReader for ExecProcess {
+    fn finish(self: Box<Self>) -> Result<()> {
+        self.finish_file()
+    }
+
+    fn cancel(self: Box<Self>) -> Result<()> {
+        self.abort()
+    }
+}
+
+impl FileWriter for ExecProcess {
+    fn finish(self: Box<Self>) -> Result<()> {
+        self.finish_file()
+    }
+
+    fn cancel(self: Box<Self>) -> Result<()> {
+        self.abort()
+    }
+}
+
+impl TarReader for ExecProcess {
+    fn finish(self: Box<Self>) -> Result<Vec<String>> {
+        let (success, output) = self.report()?;
+        tar_result(success, &output)
+    }
+
+    fn cancel(self: Box<Self>) -> Result<()> {
+        self.abort()
+    }
+}
+
+impl TarWriter for ExecProcess {
+    fn finish(self: Box<Self>) -> Result<Vec<String>> {
+        let (success, output) = self.report()?;
+        tar_result(success, &output)
+    }
+
+    fn cancel(self: Box<Self>) -> Result<()> {
+        self.abort()
+    }
+}
+
+/// File written through staging file, which is copied to destination with `su` on finish,
+/// since `su` can not reliably pass binary data from adb
+struct SuWriter {
+    upload: ExecProcess,
+    device: Device,
+    /// Root command that moves staged file into place
+    commit: String,
+    staging: String,
+}
+
+impl SuWriter {
+    /// Waits for staging upload and runs commit command. Returns commit command output
+    fn commit(self) -> Result<AdbProcess> {
+        if let Err(err) = self.upload.finish_file() {
+            let _ = self
+                .device
+                .shell_as_user(&format!("rm -f {}", shell_quote(&self.staging)));
+            return Err(err);
+        }
+
+        let mut adb = self.device.command();
+        adb.args(vec!["shell", &self.device.wrap(&self.commit)]);
+        Ok(AdbProcess::spawn(adb, Stdio::null(), Stdio::piped())?.collect_stdout())
+    }
+
+    fn abort(self) -> Result<()> {
+        self.upload.abort()?;
+        self.device
+            .shell_as_user(&format!("rm -f {}", shell_quote(&self.staging)))?;
+        Ok(())
+    }
+}
+
+impl Write for SuWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.upload.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.upload.flush()
+    }
+}
+
+impl FileWriter for SuWriter {
+    fn finish(self: Box<Self>) -> Result<()> {
+        self.commit()?.finish()
+    }
+
+    fn cancel(self: Box<Self>) -> Result<()> {
+        self.abort()
+    }
+}
+
+impl TarWriter for SuWriter {
+    fn finish(self: Box<Self>) -> Result<Vec<String>> {
+        self.commit()?.finish_tar()
+    }
+
+    fn cancel(self: Box<Self>) -> Result<()> {
+        self.abort()
+    }
+}
 
 #[derive(Clone)]
 pub struct Device {
     name: String,
     authorized: bool,
     working_directory: String,
+    privilege: Privilege,
 }
 
 impl Device {
@@ -21,7 +439,8 @@ impl Device {
             Some(Self {
                 name: device_info[0].to_string(),
                 authorized: device_info[1] == "device",
-                working_directory: "/".to_string(),
+                working_directory: format!("{}/", config::get().device_start.trim_end_matches('/')),
+                privilege: Privilege::Shell,
             })
         } else {
             None
@@ -29,54 +448,242 @@ impl Device {
     }
 
     pub fn get_name(&self) -> String {
-        return self.name.clone();
+        self.name.clone()
+    }
+
+    pub fn is_authorized(&self) -> bool {
+        self.authorized
     }
 
-    pub fn transfer_file(&self, file: String) -> Result<()> {
-        let mut adb = Command::new("adb");
+    /// Gets full device path of file relative to working directory
+    pub fn get_path(&self, path: &str) -> String {
+        format!(
+            "{}/{}",
+            self.working_directory.trim_end_matches('/'),
+            path.trim_end_matches('/')
+        )
+    }
+
+    fn command(&self) -> Command {
+        let mut adb = Command::new(&config::get().adb_path);
         adb.arg("-s");
-        adb.arg(self.get_name());
-        adb.args(vec!["push", file.as_str(), &self.working_directory, "-p"]);
+        adb.arg(&self.name);
+        adb
+    }
 
-        log::info!("{} to {}", file, self.working_directory);
+    /// Wraps shell command so it runs with current privilege
+    fn wrap(&self, command: &str) -> String {
+        match &self.privilege {
+            Privilege::Su => format!("su -c {}", shell_quote(command)),
+            Privilege::RunAs(package) => format!(
+                "run-as {} sh -c {}",
+                shell_quote(package),
+                shell_quote(command)
+            ),
+            Privilege::Shell | Privilege::AdbRoot => command.to_string(),
+        }
+    }
 
-        let output_full = adb
-            .output()
-            .with_context(|| format!("Failed to create adb process"))?;
+    /// Runs shell command on device with current privilege and returns its output
+    fn shell(&self, command: &str) -> Result<String> {
+        self.shell_as_user(&self.wrap(command))
+    }
 
-        let output_str = String::from_utf8_lossy(&output_full.stdout).to_string();
-        let output_error = String::from_utf8_lossy(&output_full.stderr).to_string();
+    /// Runs shell command on device as adbd user and returns its output
+    fn shell_as_user(&self, command: &str) -> Result<String> {
+        let mut adb = self.command();
+        adb.args(vec!["shell", command]);
 
-        if !output_full.status.success() {
-            return Err(anyhow!(output_str));
+        let adb_output_full = adb.output().context("Failed to create adb process")?;
+        if !adb_output_full.status.success() {
+            return Err(anyhow!(
+                "{}",
+                String::from_utf8_lossy(&adb_output_full.stderr)
+            ));
         }
 
-        if output_str.is_empty() {
-            return Err(anyhow!(output_error));
+        Ok(String::from_utf8_lossy(&adb_output_full.stdout).to_string())
+    }
+
+    /// Runs adb command, like `root`, and waits until device reconnects
+    fn reconnect(&self, command: &str) -> Result<()> {
+        let mut adb = self.command();
+        adb.arg(command);
+        let output = adb.output().context("Failed to create adb process")?;
+        log::info!(
+            "adb {}: {}",
+            command,
+            String::from_utf8_lossy(&output.stdout)
+        );
+
+        let mut adb = self.command();
+        adb.arg("wait-for-device");
+        adb.status().context("Failed to create adb process")?;
+        Ok(())
+    }
+
+    /// Checks whether commands with current privilege run as root
+    fn check_root(&self) -> Result<()> {
+        let uid = self.shell("id -u").unwrap_or_default();
+        if uid.trim() != "0" {
+            return Err(anyhow!(
+                "Device {} did not grant root access. Is it a rooted or engineering build?",
+                self.name
+            ));
+        }
+        Ok(())
+    }
+
+    /// Switches commands to `privilege` and checks that it grants root access
+    fn enter_privilege(&mut self, privilege: Privilege) -> Result<()> {
+        if self.privilege == Privilege::AdbRoot {
+            self.reconnect("unroot")?;
+        }
+        if privilege == Privilege::AdbRoot {
+            self.reconnect("root")?;
         }
+        self.privilege = privilege.clone();
 
-        log::info!("{}", output_str);
+        if privilege != Privilege::Shell {
+            if let Err(err) = self.check_root() {
+                if privilege == Privilege::AdbRoot {
+                    let _ = self.reconnect("unroot");
+                }
+                self.privilege = Privilege::Shell;
+                return Err(err);
+            }
+        }
         Ok(())
     }
+
+    /// Lists debuggable packages installed by user, which can be opened with `run_as`
+    pub fn list_packages(&self) -> Result<Vec<String>> {
+        // Probing all packages in one command is much faster than running adb for each
+        let output = self.shell_as_user(
+            "for package in $(pm list packages -3 | sed 's/^package://'); do \
+             run-as \"$package\" true 2>/dev/null && echo \"$package\"; done; true",
+        )?;
+        let mut packages = output
+            .lines()
+            .map(|line| line.trim().to_string())
+            .filter(|package| !package.is_empty())
+            .collect::<Vec<String>>();
+        packages.sort();
+        Ok(packages)
+    }
+
+    /// Opens sandbox `/data/data/<package>` of debuggable app. All commands run as app user
+    pub fn run_as(mut self, package: &str) -> Result<Self> {
+        self.privilege = Privilege::RunAs(package.to_string());
+        self.shell("true").map_err(|err| {
+            anyhow!(
+                "Can not access {} with run-as, is it a debuggable build? {}",
+                package,
+                err
+            )
+        })?;
+        self.working_directory = format!("/data/data/{}/", package);
+        Ok(self)
+    }
+
+    /// Uploads file into staging directory, so that `commit` command run as root can move it into
+    /// protected location
+    fn open_staged_write(&self, staging: String, commit: String) -> Result<SuWriter> {
+        Ok(SuWriter {
+            upload: self.exec_in(&format!("cat > {}", shell_quote(&staging)))?,
+            device: self.clone(),
+            commit,
+            staging,
+        })
+    }
+
+    /// Starts device `command` reading uploaded data from stdin
+    fn exec_in(&self, command: &str) -> Result<ExecProcess> {
+        self.exec("exec-in", command)
+    }
+
+    /// Starts device `command` whose stdout is downloaded
+    fn exec_out(&self, command: &str) -> Result<ExecProcess> {
+        self.exec("exec-out", command)
+    }
+
+    /// Runs `command` with adb `service`. Messages of command go into report file, and of
+    /// upload commands stdout too
+    fn exec(&self, service: &str, command: &str) -> Result<ExecProcess> {
+        // Reading and writing tar on same device runs two commands at once, so each gets own report
+        static NEXT_REPORT: AtomicUsize = AtomicUsize::new(0);
+        let report = format!(
+            "{}/.adbexplorer-report-{}-{}",
+            SU_STAGING_DIRECTORY,
+            std::process::id(),
+            NEXT_REPORT.fetch_add(1, Ordering::Relaxed)
+        );
+        let upload = service == "exec-in";
+        let redirect =
+            if upload { "> {} 2>&1" } else { "2> {}" }.replace("{}", &shell_quote(&report));
+
+        let mut adb = self.command();
+        adb.args(vec![
+            service,
+            &format!(
+                "{{ {}; }} {}; echo $? > {}",
+                command,
+                redirect,
+                shell_quote(&format!("{}.status", report))
+            ),
+        ]);
+
+        let (stdin, stdout) = if upload {
+            (Stdio::piped(), Stdio::null())
+        } else {
+            (Stdio::null(), Stdio::piped())
+        };
+        Ok(ExecProcess {
+            process: AdbProcess::spawn(adb, stdin, stdout)?,
+            device: self.clone(),
+            report,
+        })
+    }
+
+    /// Gets staging file path for file with device path `path`
+    fn staging_path(&self, path: &str) -> String {
+        format!(
+            "{}/.adbexplorer-staging-{}",
+            SU_STAGING_DIRECTORY,
+            path.trim_matches('/').replace('/', "_")
+        )
+    }
 }
 
 impl FileOperations for Device {
+    fn get_source_name(&self) -> String {
+        match &self.privilege {
+            Privilege::Shell => format!("Device {}", self.name),
+            Privilege::Su => format!("Device {} [su]", self.name),
+            Privilege::AdbRoot => format!("Device {} [root]", self.name),
+            Privilege::RunAs(package) => format!("Device {} [run-as {}]", self.name, package),
+        }
+    }
+
+    fn get_source_key(&self) -> Option<String> {
+        Some(format!("device:{}", self.name))
+    }
+
     fn get_files(&mut self) -> Result<Vec<String>> {
-        let mut adb = Command::new("adb");
+        let mut adb = self.command();
         let mut files = Vec::new();
 
-        adb.arg("-s");
-        adb.arg(&self.name);
-        adb.args(vec!["shell", "ls", &self.working_directory, "-p"]);
+        adb.args(vec![
+            "shell",
+            &self.wrap(&format!("ls -p {}", shell_quote(&self.working_directory))),
+        ]);
 
-        let adb_output_full = adb
-            .output()
-            .with_context(|| format!("Failed to create adb process"))?;
+        let adb_output_full = adb.output().context("Failed to create adb process")?;
 
         let output_str = String::from_utf8_lossy(&adb_output_full.stdout).to_string();
         let output_error = String::from_utf8_lossy(&adb_output_full.stderr).to_string();
 
-        if output_str.is_empty() {
+        if output_str.is_empty() && !output_error.is_empty() {
             return Err(anyhow!(output_error));
         }
 
@@ -86,15 +693,69 @@ impl FileOperations for Device {
         Ok(files)
     }
 
+    fn get_entries(&mut self) -> Result<Vec<FileEntry>> {
+        // `ls -F` marks symlinks and executables, `find -L -type l` finds symlinks with missing target
+        // and `[ -d ]` tells which symlinks point to directories
+        let output = self.shell(&format!(
+            "cd {} && {{ ls -F; echo {}; find -L . -maxdepth 1 -type l; echo {}; \
+             for f in *; do [ -L \"$f\" ] && [ -d \"$f\" ] && echo \"$f\"; done; true; }}",
+            shell_quote(&self.working_directory),
+            BROKEN_LINKS_MARKER,
+            DIRECTORY_LINKS_MARKER
+        ))?;
+        let (listing, links) = output
+            .split_once(BROKEN_LINKS_MARKER)
+            .unwrap_or((output.as_str(), ""));
+        let (broken, directory_links) = links
+            .split_once(DIRECTORY_LINKS_MARKER)
+            .unwrap_or((links, ""));
+        let broken = broken
+            .lines()
+            .filter_map(|line| line.strip_prefix("./"))
+            .collect::<Vec<&str>>();
+        let directory_links = directory_links.lines().collect::<Vec<&str>>();
+
+        let mut entries = Vec::new();
+        for line in listing.lines().filter(|line| !line.is_empty()) {
+            let (name, marker) = match line.char_indices().last() {
+                Some((idx, marker @ ('@' | '*' | '|' | '='))) => (&line[..idx], Some(marker)),
+                _ => (line, None),
+            };
+            let kind = match marker {
+                _ if name.ends_with('/') => FileKind::Directory,
+                Some('@') if broken.contains(&name) => FileKind::BrokenSymlink,
+                Some('@') => FileKind::Symlink,
+                Some('*') => FileKind::Executable,
+                Some(_) => FileKind::File,
+                None => FileEntry::kind_by_extension(name),
+            };
+            // Symlinks to directories are entered like directories, as in local file list
+            let name = if marker == Some('@') && directory_links.contains(&name) {
+                format!("{}/", name)
+            } else {
+                name.to_string()
+            };
+            entries.push(FileEntry::new(name, kind));
+        }
+        Ok(entries)
+    }
+
     fn change_directory_rel(&mut self, path: &str) {
-        self.working_directory = format!("{}{}", self.working_directory, path);
+        if self.is_directory(path.to_string()) {
+            self.working_directory = format!("{}{}", self.working_directory, path);
+        }
+    }
+
+    fn change_directory(&mut self, path: &str) {
+        self.working_directory = format!("{}/", path.trim_end_matches('/'));
     }
 
-    fn level_up_files(&mut self) -> Result<Vec<String>> {
-        let mut splited_path = self.working_directory.split("/").collect::<Vec<&str>>();
-        splited_path.remove(splited_path.len() - 1);
-        self.working_directory = splited_path.join("/");
-        self.get_files()
+    fn level_up(&mut self) {
+        let trimmed = self.working_directory.trim_end_matches('/');
+        self.working_directory = match trimmed.rfind('/') {
+            Some(pos) => trimmed[..pos + 1].to_string(),
+            None => "/".to_string(),
+        };
     }
 
     fn is_directory(&self, path: String) -> bool {
@@ -104,6 +765,247 @@ impl FileOperations for Device {
     fn get_working_directory(&self) -> &str {
         self.working_directory.as_str()
     }
+
+    fn file_info(&mut self, path: &str) -> Result<Option<FileInfo>> {
+        let mut adb = self.command();
+        adb.args(vec![
+            "shell",
+            &self.wrap(&format!(
+                "stat -c '%s %Y' {}",
+                shell_quote(&self.get_path(path))
+            )),
+        ]);
+        let output = adb.output().context("Failed to create adb process")?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        let mut fields = stdout.split_whitespace();
+        if let (Some(Ok(size)), Some(Ok(modified)), None) = (
+            fields.next().map(str::parse),
+            fields.next().map(str::parse),
+            fields.next(),
+        ) {
+            return Ok(Some(FileInfo { size, modified }));
+        }
+
+        // Without shell protocol errors come in stdout
+        let message = format!("{}{}", stdout, String::from_utf8_lossy(&output.stderr));
+        if message.contains("No such file") {
+            Ok(None)
+        } else {
+            Err(anyhow!("Can not check {}: {}", path, message.trim()))
+        }
+    }
+
+    fn walk(&mut self, path: &str) -> Result<Vec<String>> {
+        let path = path.trim_end_matches('/');
+        let output_str = self.shell(&format!(
+            "find {} -type f",
+            shell_quote(&self.get_path(path))
+        ))?;
+        let prefix = self.get_path("");
+
+        Ok(output_str
+            .lines()
+            .map(|line| line.trim_start_matches(&prefix).to_string())
+            .collect())
+    }
+
+    fn open_read(&mut self, path: &str, offset: u64) -> Result<Box<dyn FileReader>> {
+        Ok(Box::new(self.exec_out(&self.wrap(&format!(
+            "tail -c +{} {}",
+            offset + 1,
+            shell_quote(&self.get_path(path))
+        )))?))
+    }
+
+    fn read_range(&mut self, path: &str, offset: u64, len: u64) -> Result<Vec<u8>> {
+        let path = shell_quote(&self.get_path(path));
+        let command = if offset == 0 {
+            format!("head -c {} {}", len, path)
+        } else {
+            format!("tail -c +{} {} | head -c {}", offset + 1, path, len)
+        };
+        let mut process = self.exec_out(&self.wrap(&command))?;
+        let mut data = Vec::new();
+        process.read_to_end(&mut data)?;
+
+        // Status of pipeline is the one of `head`, so failed `tail` is only seen in its messages
+        let (success, messages) = process.report()?;
+        if !success || !messages.trim().is_empty() {
+            return Err(anyhow!("{}", messages.trim()));
+        }
+        Ok(data)
+    }
+
+    fn open_write(&mut self, path: &str, append: bool) -> Result<Box<dyn FileWriter>> {
+        let full_path = self.get_path(path);
+        let parent = full_path
+            .rsplit_once('/')
+            .map(|(parent, _)| parent)
+            .unwrap_or("");
+        let parent = shell_quote(if parent.is_empty() { "/" } else { parent });
+        let redirect = if append { ">>" } else { ">" };
+
+        log::info!("Writing {}", full_path);
+
+        if self.privilege == Privilege::Su {
+            let staging = self.staging_path(&full_path);
+            let commit = format!(
+                "mkdir -p {} && cat {} {} {} && rm -f {1}",
+                parent,
+                shell_quote(&staging),
+                redirect,
+                shell_quote(&full_path)
+            );
+            return Ok(Box::new(self.open_staged_write(staging, commit)?));
+        }
+
+        Ok(Box::new(self.exec_in(&self.wrap(&format!(
+            "mkdir -p {} && cat {} {}",
+            parent,
+            redirect,
+            shell_quote(&full_path)
+        )))?))
+    }
+
+    fn rename(&mut self, from: &str, to: &str) -> Result<()> {
+        self.shell(&format!(
+            "mv -f {} {}",
+            shell_quote(&self.get_path(from)),
+            shell_quote(&self.get_path(to))
+        ))?;
+        Ok(())
+    }
+
+    fn overwrite(&mut self, from: &str, to: &str) -> Result<()> {
+        // Copies on device, without transferring file through adb again
+        self.shell(&format!(
+            "cat {} > {}",
+            shell_quote(&self.get_path(from)),
+            shell_quote(&self.get_path(to))
+        ))?;
+        Ok(())
+    }
+
+    fn remove(&mut self, path: &str) -> Result<()> {
+        self.shell(&format!("rm -f {}", shell_quote(&self.get_path(path))))?;
+        Ok(())
+    }
+
+    fn volumes(&mut self) -> Result<Vec<Volume>> {
+        // `sm` tells SD cards (block major 179) from USB drives, but is not available everywhere
+        let kinds = self
+            .shell_as_user("sm list-volumes public")
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                let id = fields.next()?;
+                let uuid = fields.nth(1)?;
+                let kind = if id.starts_with("public:179,") {
+                    "SD card"
+                } else {
+                    "USB drive"
+                };
+                Some((uuid.to_string(), kind))
+            })
+            .collect::<Vec<(String, &str)>>();
+
+        let output = self.shell(
+            "for dir in /sdcard /storage/*; do echo \"$dir $(df -k \"$dir\" | tail -n 1)\"; done",
+        )?;
+        let mut volumes = Vec::new();
+
+        for line in output.lines() {
+            let fields = line.split_whitespace().collect::<Vec<&str>>();
+            if fields.len() < 5 {
+                continue;
+            }
+            let path = fields[0];
+            let (total, free) = match (fields[2].parse::<u64>(), fields[4].parse::<u64>()) {
+                (Ok(total), Ok(free)) => (total * 1024, free * 1024),
+                _ => continue,
+            };
+
+            let dir_name = path.trim_start_matches("/storage/");
+            let name = match dir_name {
+                "/sdcard" => "Internal storage".to_string(),
+                "emulated" | "self" => continue,
+                _ => match kinds.iter().find(|(uuid, _)| uuid == dir_name) {
+                    Some((_, kind)) => format!("{} {}", kind, dir_name),
+                    None => format!("External storage {}", dir_name),
+                },
+            };
+
+            volumes.push(Volume {
+                name,
+                path: path.to_string(),
+                total,
+                free,
+            });
+        }
+
+        Ok(volumes)
+    }
+
+    fn has_privilege_modes(&self) -> bool {
+        !matches!(self.privilege, Privilege::RunAs(_))
+    }
+
+    fn switch_privilege(&mut self) -> Result<()> {
+        if let Privilege::RunAs(package) = &self.privilege {
+            return Err(anyhow!(
+                "App sandbox of {} is only accessible as its app user",
+                package
+            ));
+        }
+
+        let mut next = self.privilege.next();
+        loop {
+            match self.enter_privilege(next.clone()) {
+                Ok(()) => break,
+                // Engineering builds often have no su but allow adb root, so try it too
+                Err(err) if next == Privilege::Su => {
+                    log::warn!("{}", err);
+                    next = next.next();
+                }
+                Err(err) => {
+                    self.privilege = Privilege::Shell;
+                    return Err(err);
+                }
+            }
+        }
+
+        log::info!("Device {} switched to {:?}", self.name, self.privilege);
+        Ok(())
+    }
+
+    fn open_tar_read(&mut self, path: &str) -> Result<Box<dyn TarReader>> {
+        Ok(Box::new(self.exec_out(&self.wrap(&format!(
+            "cd {} && tar -cvf - {}",
+            shell_quote(&self.get_path("")),
+            shell_quote(path.trim_end_matches('/'))
+        )))?))
+    }
+
+    fn open_tar_write(&mut self) -> Result<Box<dyn TarWriter>> {
+        let working_directory = shell_quote(&self.get_path(""));
+
+        if self.privilege == Privilege::Su {
+            let staging = self.staging_path(&format!("{}.tar", self.get_path("")));
+            let commit = format!(
+                "mkdir -p {0} && cd {0} && tar -xvf {1}; status=$?; rm -f {1}; exit $status",
+                working_directory,
+                shell_quote(&staging)
+            );
+            return Ok(Box::new(self.open_staged_write(staging, commit)?));
+        }
+
+        Ok(Box::new(self.exec_in(&self.wrap(&format!(
+            "mkdir -p {0} && cd {0} && tar -xvf -",
+            working_directory
+        )))?))
+    }
 }
 
 pub struct Adb {
@@ -112,12 +1014,11 @@ pub struct Adb {
 
 impl Adb {
     pub fn new() -> Result<Self> {
-        let mut adb = Command::new("adb");
+        let mut adb = Command::new(&config::get().adb_path);
         adb.arg("start-server");
 
-        adb.status().with_context(|| {
-            format!("Failed to start adb server, check for adb installation in system")
-        })?;
+        adb.status()
+            .context("Failed to start adb server, check for adb installation in system")?;
 
         Ok(Self {
             devices: Vec::new(),
@@ -125,15 +1026,13 @@ impl Adb {
     }
 
     pub fn populate_devices(&mut self) -> Result<()> {
-        let mut adb = Command::new("adb");
+        self.devices.clear();
+        let mut adb = Command::new(&config::get().adb_path);
         adb.arg("devices");
 
-        let adb_output_full = adb
-            .output()
-            .with_context(|| format!("Failed to create adb process"))?;
+        let adb_output_full = adb.output().context("Failed to create adb process")?;
 
         for device_str in String::from_utf8_lossy(&adb_output_full.stdout)
-            .to_string()
             .split("\n")
             .skip(1)
         {
@@ -145,3 +1044,51 @@ impl Adb {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_tar_verbose_outputs() {
+        let cases: &[(&str, &[&str])] = &[
+            // toybox `tar -cvf - Camera`
+            (
+                "Camera/\nCamera/IMG_20240101_120000.jpg\nCamera/VID 0001.mp4\n",
+                &[
+                    "Camera",
+                    "Camera/IMG_20240101_120000.jpg",
+                    "Camera/VID 0001.mp4",
+                ],
+            ),
+            // busybox `tar -xvf -` of archive made in working directory
+            (
+                "./\n./notes/\n./notes/todo.txt\n",
+                &["notes", "notes/todo.txt"],
+            ),
+            // toybox and busybox errors are mixed into the list
+            (
+                "data/\ntar: 'data/secret': Permission denied\ndata/public.txt\n",
+                &["data", "data/public.txt"],
+            ),
+            ("tar: can't open 'data/secret': Permission denied\n", &[]),
+            // stdout and stderr are joined with empty line
+            ("a.txt\n\nb.txt", &["a.txt", "b.txt"]),
+            ("", &[]),
+        ];
+
+        for (output, files) in cases {
+            assert_eq!(parse_tar_verbose(output), *files, "output {:?}", output);
+        }
+    }
+
+    #[test]
+    fn tar_result_fails_only_without_files() {
+        assert_eq!(
+            tar_result(false, "dir/\ndir/a\n").unwrap(),
+            ["dir", "dir/a"]
+        );
+        assert!(tar_result(false, "tar: dir: No such file or directory\n").is_err());
+        assert!(tar_result(true, "").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,337 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, PipeReader, Read},
+    path::{Path, PathBuf},
+    thread::{self, JoinHandle},
+};
+
+use anyhow::{anyhow, Context, Result};
+use flate2::read::GzDecoder;
+
+use crate::file_operations::{FileInfo, FileOperations, FileReader, FileWriter};
+
+#[derive(Clone, Copy, PartialEq)]
+enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveKind {
+    fn detect(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveKind::TarGz)
+        } else if name.ends_with(".tar") {
+            Some(ArchiveKind::Tar)
+        } else if [".zip", ".apk", ".jar", ".aab"]
+            .iter()
+            .any(|extension| name.ends_with(extension))
+        {
+            Some(ArchiveKind::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+/// Days since unix epoch for civil date, inverse of `FileInfo::format_modified`
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// Read-only file list of zip (apk, jar) or tar (tar.gz) archive contents
+pub struct Archive {
+    path: PathBuf,
+    kind: ArchiveKind,
+    /// Files keyed by path inside archive without leading slash.
+    /// Directories are not stored, they are derived from file paths
+    entries: BTreeMap<String, FileInfo>,
+    /// Names zip entries are stored under, keyed by path in `entries`, which has leading slash
+    /// trimmed
+    zip_names: BTreeMap<String, String>,
+    /// Working directory inside archive, starts and ends with slash
+    working_directory: String,
+}
+
+impl Archive {
+    /// Checks whether file looks like a supported archive by its extension
+    pub fn is_archive(path: &Path) -> bool {
+        ArchiveKind::detect(path).is_some()
+    }
+
+    /// Opens archive and reads its index
+    pub fn open(path: PathBuf) -> Result<Self> {
+        let kind = ArchiveKind::detect(&path)
+            .ok_or_else(|| anyhow!("{} is not a supported archive", path.display()))?;
+        let mut entries = BTreeMap::new();
+        let mut zip_names = BTreeMap::new();
+
+        match kind {
+            ArchiveKind::Zip => {
+                let mut zip = zip::ZipArchive::new(File::open(&path)?)?;
+                for idx in 0..zip.len() {
+                    let file = zip.by_index(idx)?;
+                    if file.is_dir() {
+                        continue;
+                    }
+
+                    let date = file.last_modified();
+                    let days =
+                        days_from_civil(date.year() as i64, date.month() as i64, date.day() as i64);
+                    let seconds = date.hour() as i64 * 3600
+                        + date.minute() as i64 * 60
+                        + date.second() as i64;
+
+                    let name = file.name().trim_start_matches('/').to_string();
+                    entries.insert(
+                        name.clone(),
+                        FileInfo {
+                            size: file.size(),
+                            modified: (days * 86400 + seconds).max(0) as u64,
+                        },
+                    );
+                    zip_names.insert(name, file.name().to_string());
+                }
+            }
+            ArchiveKind::Tar | ArchiveKind::TarGz => {
+                let mut tar = tar::Archive::new(Self::open_tar_stream(&path, kind)?);
+                for file in tar.entries()? {
+                    let file = file?;
+                    if !file.header().entry_type().is_file() {
+                        continue;
+                    }
+
+                    let name = file.path()?.to_string_lossy().to_string();
+                    entries.insert(
+                        name.trim_start_matches("./")
+                            .trim_start_matches('/')
+                            .to_string(),
+                        FileInfo {
+                            size: file.header().size()?,
+                            modified: file.header().mtime()?,
+                        },
+                    );
+                }
+            }
+        }
+
+        Ok(Self {
+            path,
+            kind,
+            entries,
+            zip_names,
+            working_directory: "/".to_string(),
+        })
+    }
+
+    fn open_tar_stream(path: &Path, kind: ArchiveKind) -> Result<Box<dyn Read + Send>> {
+        let file = File::open(path)?;
+        if kind == ArchiveKind::TarGz {
+            Ok(Box::new(GzDecoder::new(file)))
+        } else {
+            Ok(Box::new(file))
+        }
+    }
+
+    /// Gets path inside archive of file relative to working directory
+    fn get_path(&self, path: &str) -> String {
+        format!(
+            "{}{}",
+            self.working_directory.trim_start_matches('/'),
+            path.trim_end_matches('/')
+        )
+    }
+
+    /// Checks whether `path` inside archive is a directory, i.e. some file is stored below it
+    fn has_directory(&self, path: &str) -> bool {
+        let prefix = format!("{}/", path);
+        path.is_empty()
+            || self
+                .entries
+                .range(prefix.clone()..)
+                .next()
+                .is_some_and(|(name, _)| name.starts_with(&prefix))
+    }
+
+    /// Copies contents of archive entry `name` starting from `offset` byte into `output`.
+    /// Zip entries are looked up by name they are stored under
+    fn extract(
+        path: &Path,
+        kind: ArchiveKind,
+        name: &str,
+        offset: u64,
+        output: &mut dyn io::Write,
+    ) -> Result<()> {
+        match kind {
+            ArchiveKind::Zip => {
+                let mut zip = zip::ZipArchive::new(File::open(path)?)?;
+                let mut file = zip.by_name(name)?;
+                io::copy(&mut (&mut file).take(offset), &mut io::sink())?;
+                io::copy(&mut file, output)?;
+                return Ok(());
+            }
+            ArchiveKind::Tar | ArchiveKind::TarGz => {
+                let mut tar = tar::Archive::new(Self::open_tar_stream(path, kind)?);
+                for file in tar.entries()? {
+                    let mut file = file?;
+                    let file_name = file.path()?.to_string_lossy().to_string();
+                    if file_name.trim_start_matches("./").trim_start_matches('/') == name {
+                        io::copy(&mut (&mut file).take(offset), &mut io::sink())?;
+                        io::copy(&mut file, output)?;
+                        return Ok(());
+                    }
+                }
+            }
+        }
+
+        Err(anyhow!("{} not found in archive", name))
+    }
+}
+
+/// Archive entry contents, decompressed in background thread
+struct ArchiveReader {
+    pipe: PipeReader,
+    thread: JoinHandle<Result<()>>,
+}
+
+impl Read for ArchiveReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.pipe.read(buf)
+    }
+}
+
+impl FileReader for ArchiveReader {
+    fn finish(self: Box<Self>) -> Result<()> {
+        drop(self.pipe);
+        self.thread
+            .join()
+            .map_err(|_| anyhow!("Archive extraction thread panicked"))?
+    }
+
+    fn cancel(self: Box<Self>) -> Result<()> {
+        drop(self.pipe);
+        let _ = self.thread.join();
+        Ok(())
+    }
+}
+
+impl FileOperations for Archive {
+    fn get_source_name(&self) -> String {
+        format!(
+            "Archive {}",
+            self.path.file_name().unwrap_or_default().to_string_lossy()
+        )
+    }
+
+    fn get_files(&mut self) -> Result<Vec<String>> {
+        let prefix = self.get_path("");
+        let mut files = Vec::new();
+
+        for name in self.entries.keys() {
+            if let Some(rel_path) = name.strip_prefix(&prefix) {
+                let file = match rel_path.find('/') {
+                    Some(pos) => format!("{}/", &rel_path[..pos]),
+                    None => rel_path.to_string(),
+                };
+
+                if files.last() != Some(&file) {
+                    files.push(file);
+                }
+            }
+        }
+
+        Ok(files)
+    }
+
+    fn change_directory_rel(&mut self, path: &str) {
+        let full_path = self.get_path(path);
+        if self.has_directory(&full_path) {
+            self.working_directory = format!("/{}/", full_path);
+        }
+    }
+
+    fn change_directory(&mut self, path: &str) {
+        let full_path = path.trim_matches('/');
+        if self.has_directory(full_path) {
+            self.working_directory = if full_path.is_empty() {
+                "/".to_string()
+            } else {
+                format!("/{}/", full_path)
+            };
+        }
+    }
+
+    fn level_up(&mut self) {
+        let trimmed = self.working_directory.trim_end_matches('/');
+        self.working_directory = match trimmed.rfind('/') {
+            Some(pos) => trimmed[..pos + 1].to_string(),
+            None => "/".to_string(),
+        };
+    }
+
+    fn is_directory(&self, path: String) -> bool {
+        path.ends_with('/')
+    }
+
+    fn get_working_directory(&self) -> &str {
+        self.working_directory.as_str()
+    }
+
+    fn file_info(&mut self, path: &str) -> Result<Option<FileInfo>> {
+        Ok(self.entries.get(&self.get_path(path)).copied())
+    }
+
+    fn walk(&mut self, path: &str) -> Result<Vec<String>> {
+        let full_path = self.get_path(path);
+        if self.entries.contains_key(&full_path) {
+            return Ok(vec![path.trim_end_matches('/').to_string()]);
+        }
+
+        let working_prefix = self.get_path("");
+        let prefix = format!("{}/", full_path);
+
+        Ok(self
+            .entries
+            .keys()
+            .filter(|name| name.starts_with(&prefix))
+            .map(|name| name[working_prefix.len()..].to_string())
+            .collect())
+    }
+
+    fn open_read(&mut self, path: &str, offset: u64) -> Result<Box<dyn FileReader>> {
+        let (pipe, mut writer) = io::pipe().context("Failed to create pipe")?;
+        let archive_path = self.path.clone();
+        let kind = self.kind;
+        let name = self.get_path(path);
+        let name = self.zip_names.get(&name).cloned().unwrap_or(name);
+
+        let thread =
+            thread::spawn(move || Self::extract(&archive_path, kind, &name, offset, &mut writer));
+
+        Ok(Box::new(ArchiveReader { pipe, thread }))
+    }
+
+    fn open_write(&mut self, _path: &str, _append: bool) -> Result<Box<dyn FileWriter>> {
+        Err(anyhow!("Archives are read-only"))
+    }
+
+    fn rename(&mut self, _from: &str, _to: &str) -> Result<()> {
+        Err(anyhow!("Archives are read-only"))
+    }
+
+    fn remove(&mut self, _path: &str) -> Result<()> {
+        Err(anyhow!("Archives are read-only"))
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,107 @@
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::config::config_dir;
+
+/// How many recently visited directories are remembered per source
+const RECENT_LIMIT: usize = 20;
+
+/// Bookmarked and recently visited directories, keyed by file list source
+/// (see `FileOperations::get_source_key`). Stored in `bookmarks.toml` in config directory
+#[derive(Default, Serialize, Deserialize)]
+pub struct Bookmarks {
+    #[serde(default)]
+    bookmarks: BTreeMap<String, Vec<String>>,
+    /// Most recent directory first
+    #[serde(default)]
+    recent: BTreeMap<String, Vec<String>>,
+}
+
+impl Bookmarks {
+    fn path() -> PathBuf {
+        config_dir().join("bookmarks.toml")
+    }
+
+    /// Loads bookmarks, starting with empty ones if file does not exist yet.
+    /// Invalid file is moved aside, so saving new bookmarks does not overwrite it
+    pub fn load() -> Result<Self> {
+        let path = Self::path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = fs::read_to_string(&path)?;
+        toml::from_str(&contents).map_err(|err| {
+            let backup = path.with_extension("toml.invalid");
+            match fs::rename(&path, &backup) {
+                Ok(()) => anyhow!(
+                    "Invalid {}, it is moved to {}: {}",
+                    path.display(),
+                    backup.display(),
+                    err
+                ),
+                Err(_) => anyhow!("Invalid {}: {}", path.display(), err),
+            }
+        })
+    }
+
+    fn write(&self) -> Result<()> {
+        let path = Self::path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, toml::to_string(self)?)
+            .with_context(|| format!("Failed to save {}", path.display()))
+    }
+
+    /// Saves bookmarks. Failure is only logged, since it should not interrupt browsing
+    fn save(&self) {
+        if let Err(err) = self.write() {
+            log::error!("{:#}", err);
+        }
+    }
+
+    pub fn get_bookmarks(&self, key: &str) -> &[String] {
+        self.bookmarks.get(key).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn get_recent(&self, key: &str) -> &[String] {
+        self.recent.get(key).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn is_bookmarked(&self, key: &str, path: &str) -> bool {
+        self.get_bookmarks(key)
+            .iter()
+            .any(|bookmark| bookmark == path)
+    }
+
+    /// Adds bookmark if it is missing, removes it otherwise
+    pub fn toggle(&mut self, key: &str, path: &str) {
+        let bookmarks = self.bookmarks.entry(key.to_string()).or_default();
+        match bookmarks.iter().position(|bookmark| bookmark == path) {
+            Some(idx) => {
+                bookmarks.remove(idx);
+            }
+            None => {
+                bookmarks.push(path.to_string());
+                bookmarks.sort();
+            }
+        }
+        self.save()
+    }
+
+    /// Records visit of directory. Saves only if it is not already the most recent one
+    pub fn visit(&mut self, key: &str, path: &str) {
+        let recent = self.recent.entry(key.to_string()).or_default();
+        if recent.first().map(String::as_str) == Some(path) {
+            return;
+        }
+
+        recent.retain(|dir| dir != path);
+        recent.insert(0, path.to_string());
+        recent.truncate(RECENT_LIMIT);
+        self.save()
+    }
+}
@@ -1,6 +1,6 @@
-use console_engine::{Color, ConsoleEngine, pixel, screen::Screen};
-use Into;
+use console_engine::{pixel, screen::Screen, ConsoleEngine};
 
+use crate::config;
 
 /// Bars interface
 #[derive(Clone)]
@@ -10,7 +10,7 @@ pub struct StateBar {
     error: bool,
     /// Screen struct
     screen: Screen,
-    /// Y position of the bar
+    /// Y position of the bar, above hint bar
     pub y: u32,
 }
 
@@ -20,15 +20,22 @@ impl StateBar {
         Self {
             message: String::new(),
             error: false,
-            screen: Screen::new(engine.get_width().into(), 1),
-            y: engine.get_height() as u32 - 1,
+            screen: Screen::new(engine.get_width(), 1),
+            y: engine.get_height() - 2,
         }
     }
 
     /// Draws a bar and its widgets
     pub fn draw(&mut self) -> &Screen {
-        self.screen.fill(pixel::pxl_bg(' ', Color::Blue));
-        self.screen.print_fbg(0, 0, self.message.as_str(), Color::Reset, Color::Blue);
+        let theme = &config::get().theme;
+        let bg = if self.error {
+            theme.status_bar_error_bg
+        } else {
+            theme.status_bar_bg
+        };
+        self.screen.fill(pixel::pxl_bg(' ', bg));
+        self.screen
+            .print_fbg(0, 0, self.message.as_str(), theme.status_bar_fg, bg);
         &self.screen
     }
 
@@ -40,6 +47,6 @@ impl StateBar {
     pub fn resize(&mut self, w: u16, h: u16) {
         self.screen.clear();
         self.screen.resize(w as u32, 1);
-        self.y = h as u32;
+        self.y = h as u32 - 2;
     }
-}
\ No newline at end of file
+}
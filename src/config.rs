@@ -0,0 +1,476 @@
+use std::{collections::BTreeMap, env, fs, path::PathBuf, sync::OnceLock};
+
+use anyhow::{anyhow, Result};
+use console_engine::Color;
+use serde::Deserialize;
+
+use crate::keymap::{Action, KeyBinding, Keymap};
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Gets directory where configuration and user data are stored:
+/// `$XDG_CONFIG_HOME/adbexplorer`, falling back to `~/.config/adbexplorer`
+pub fn config_dir() -> PathBuf {
+    let base = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME").unwrap_or_default()).join(".config"),
+    };
+    base.join("adbexplorer")
+}
+
+/// Gets directory for cached copies of device files:
+/// `$XDG_CACHE_HOME/adbexplorer`, falling back to `~/.cache/adbexplorer`
+pub fn cache_dir() -> PathBuf {
+    let base = match env::var_os("XDG_CACHE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME").unwrap_or_default()).join(".cache"),
+    };
+    base.join("adbexplorer")
+}
+
+/// Gets path of config file
+pub fn config_path() -> PathBuf {
+    config_dir().join("config.toml")
+}
+
+/// Sets config used by the whole application. Must be called once at startup
+pub fn init(config: Config) {
+    let _ = CONFIG.set(config);
+}
+
+/// Gets application config, or defaults if config was not loaded
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+/// Colors of user interface, `[colors]` table of config file
+#[derive(Clone)]
+pub struct Theme {
+    pub status_bar_fg: Color,
+    pub status_bar_bg: Color,
+    pub status_bar_error_bg: Color,
+    pub list_fg: Color,
+    pub list_inactive_fg: Color,
+    pub list_cursor_fg: Color,
+    pub list_cursor_bg: Color,
+    pub hint_key_fg: Color,
+    pub hint_key_bg: Color,
+    pub hint_label_fg: Color,
+    pub hint_label_bg: Color,
+    pub file_directory_fg: Color,
+    pub file_symlink_fg: Color,
+    pub file_broken_symlink_fg: Color,
+    pub file_executable_fg: Color,
+    pub file_apk_fg: Color,
+    pub file_image_fg: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            status_bar_fg: Color::Reset,
+            status_bar_bg: Color::Blue,
+            status_bar_error_bg: Color::Red,
+            list_fg: Color::Reset,
+            list_inactive_fg: Color::DarkGrey,
+            list_cursor_fg: Color::Reset,
+            list_cursor_bg: Color::White,
+            hint_key_fg: Color::Reset,
+            hint_key_bg: Color::Reset,
+            hint_label_fg: Color::Black,
+            hint_label_bg: Color::Cyan,
+            file_directory_fg: Color::Blue,
+            file_symlink_fg: Color::Cyan,
+            file_broken_symlink_fg: Color::Red,
+            file_executable_fg: Color::Green,
+            file_apk_fg: Color::Yellow,
+            file_image_fg: Color::Magenta,
+        }
+    }
+}
+
+impl Theme {
+    fn color_mut(&mut self, name: &str) -> Option<&mut Color> {
+        match name {
+            "status_bar_fg" => Some(&mut self.status_bar_fg),
+            "status_bar_bg" => Some(&mut self.status_bar_bg),
+            "status_bar_error_bg" => Some(&mut self.status_bar_error_bg),
+            "list_fg" => Some(&mut self.list_fg),
+            "list_inactive_fg" => Some(&mut self.list_inactive_fg),
+            "list_cursor_fg" => Some(&mut self.list_cursor_fg),
+            "list_cursor_bg" => Some(&mut self.list_cursor_bg),
+            "hint_key_fg" => Some(&mut self.hint_key_fg),
+            "hint_key_bg" => Some(&mut self.hint_key_bg),
+            "hint_label_fg" => Some(&mut self.hint_label_fg),
+            "hint_label_bg" => Some(&mut self.hint_label_bg),
+            "file_directory_fg" => Some(&mut self.file_directory_fg),
+            "file_symlink_fg" => Some(&mut self.file_symlink_fg),
+            "file_broken_symlink_fg" => Some(&mut self.file_broken_symlink_fg),
+            "file_executable_fg" => Some(&mut self.file_executable_fg),
+            "file_apk_fg" => Some(&mut self.file_apk_fg),
+            "file_image_fg" => Some(&mut self.file_image_fg),
+            _ => None,
+        }
+    }
+}
+
+/// Parses color name like `dark_grey`, `#rrggbb` hex color or 0-255 ANSI color number
+pub fn parse_color(value: &str) -> Result<Color> {
+    if let Some(hex) = value.strip_prefix('#') {
+        let channel = |idx: usize| {
+            hex.get(idx..idx + 2)
+                .and_then(|channel| u8::from_str_radix(channel, 16).ok())
+        };
+        return match (hex.len(), channel(0), channel(2), channel(4)) {
+            (6, Some(r), Some(g), Some(b)) => Ok(Color::Rgb { r, g, b }),
+            _ => Err(anyhow!("invalid hex color \"{}\"", value)),
+        };
+    }
+
+    if let Ok(ansi) = value.parse::<u8>() {
+        return Ok(Color::AnsiValue(ansi));
+    }
+
+    Ok(match value.to_lowercase().replace('_', "").as_str() {
+        "reset" | "default" => Color::Reset,
+        "black" => Color::Black,
+        "darkgrey" | "darkgray" => Color::DarkGrey,
+        "red" => Color::Red,
+        "darkred" => Color::DarkRed,
+        "green" => Color::Green,
+        "darkgreen" => Color::DarkGreen,
+        "yellow" => Color::Yellow,
+        "darkyellow" => Color::DarkYellow,
+        "blue" => Color::Blue,
+        "darkblue" => Color::DarkBlue,
+        "magenta" => Color::Magenta,
+        "darkmagenta" => Color::DarkMagenta,
+        "cyan" => Color::Cyan,
+        "darkcyan" => Color::DarkCyan,
+        "white" => Color::White,
+        "grey" | "gray" => Color::Grey,
+        _ => return Err(anyhow!("unknown color \"{}\"", value)),
+    })
+}
+
+/// One key or list of keys
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Keys {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct RawStart {
+    local: Option<String>,
+    device: Option<String>,
+    /// Settings that are not known, reported instead of failing the whole file
+    #[serde(flatten)]
+    unknown: BTreeMap<String, toml::Value>,
+}
+
+/// Config file as written by user, before validation
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct RawConfig {
+    adb_path: Option<String>,
+    log_level: Option<String>,
+    opener: Option<String>,
+    start: RawStart,
+    keys: BTreeMap<String, Keys>,
+    colors: BTreeMap<String, String>,
+    openers: BTreeMap<String, String>,
+    /// Settings that are not known, reported instead of failing the whole file
+    #[serde(flatten)]
+    unknown: BTreeMap<String, toml::Value>,
+}
+
+/// Application settings from `config.toml` in config directory
+#[derive(Clone)]
+pub struct Config {
+    pub keymap: Keymap,
+    pub theme: Theme,
+    /// Directory local panes start in. Current directory if not set
+    pub local_start: Option<String>,
+    /// Directory device panes start in
+    pub device_start: String,
+    /// Path of adb executable
+    pub adb_path: String,
+    /// Log level of adbexplorer messages
+    pub log_level: String,
+    /// Command opening files with system handler
+    pub opener: String,
+    /// Commands opening files, by lower case extension
+    pub openers: BTreeMap<String, String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            keymap: Keymap::default(),
+            theme: Theme::default(),
+            local_start: None,
+            device_start: "/sdcard".to_string(),
+            adb_path: "adb".to_string(),
+            log_level: "debug".to_string(),
+            opener: if cfg!(target_os = "macos") {
+                "open"
+            } else {
+                "xdg-open"
+            }
+            .to_string(),
+            openers: BTreeMap::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Loads config file. Invalid settings are replaced with defaults and reported in returned
+    /// list of errors, so a typo does not prevent application from starting
+    pub fn load() -> (Self, Vec<String>) {
+        let path = config_path();
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(_) => return (Self::default(), Vec::new()),
+        };
+
+        match toml::from_str::<RawConfig>(&contents) {
+            Ok(raw) => Self::validate(raw),
+            Err(err) => (Self::default(), vec![err.to_string()]),
+        }
+    }
+
+    fn validate(raw: RawConfig) -> (Self, Vec<String>) {
+        let mut config = Self::default();
+        let mut errors = raw
+            .unknown
+            .keys()
+            .map(|name| format!("unknown setting \"{}\" is ignored", name))
+            .chain(
+                raw.start
+                    .unknown
+                    .keys()
+                    .map(|name| format!("unknown setting \"start.{}\" is ignored", name)),
+            )
+            .collect::<Vec<String>>();
+
+        if let Some(adb_path) = raw.adb_path {
+            if adb_path.is_empty() {
+                errors.push("adb_path is empty".to_string());
+            } else {
+                config.adb_path = adb_path;
+            }
+        }
+
+        if let Some(log_level) = raw.log_level {
+            let log_level = log_level.to_lowercase();
+            if ["off", "error", "warn", "info", "debug", "trace"].contains(&log_level.as_str()) {
+                config.log_level = log_level;
+            } else {
+                errors.push(format!(
+                    "log_level \"{}\" must be one of off, error, warn, info, debug, trace",
+                    log_level
+                ));
+            }
+        }
+
+        if let Some(opener) = raw.opener {
+            if opener.trim().is_empty() {
+                errors.push("opener is empty".to_string());
+            } else {
+                config.opener = opener;
+            }
+        }
+
+        for (extension, opener) in raw.openers {
+            if opener.trim().is_empty() {
+                errors.push(format!("openers.{} is empty", extension));
+            } else {
+                let extension = extension.trim_start_matches('.').to_lowercase();
+                config.openers.insert(extension, opener);
+            }
+        }
+
+        if let Some(local) = raw.start.local {
+            let local = match local.strip_prefix('~') {
+                Some(rest) => format!("{}{}", env::var("HOME").unwrap_or_default(), rest),
+                None => local,
+            };
+            if PathBuf::from(&local).is_dir() {
+                config.local_start = Some(local);
+            } else {
+                errors.push(format!("start.local \"{}\" is not a directory", local));
+            }
+        }
+
+        if let Some(device) = raw.start.device {
+            if device.starts_with('/') {
+                config.device_start = device;
+            } else {
+                errors.push(format!("start.device \"{}\" must be absolute path", device));
+            }
+        }
+
+        for (name, keys) in raw.keys {
+            let action = match Action::from_name(&name) {
+                Some(action) => action,
+                None => {
+                    errors.push(format!("keys: unknown action \"{}\"", name));
+                    continue;
+                }
+            };
+
+            let keys = match keys {
+                Keys::One(key) => vec![key],
+                Keys::Many(keys) => keys,
+            };
+            match keys
+                .iter()
+                .map(|key| KeyBinding::parse(key))
+                .collect::<Result<Vec<KeyBinding>>>()
+            {
+                Ok(keys) => config.keymap.bind(action, keys),
+                Err(err) => errors.push(format!("keys.{}: {}", name, err)),
+            }
+        }
+
+        for (key, first, second) in config.keymap.find_conflicts() {
+            errors.push(format!(
+                "keys: {} is bound to both {} and {}",
+                key,
+                first.name(),
+                second.name()
+            ));
+        }
+
+        for (name, value) in raw.colors {
+            match config.theme.color_mut(&name) {
+                Some(color) => match parse_color(&value) {
+                    Ok(parsed) => *color = parsed,
+                    Err(err) => errors.push(format!("colors.{}: {}", name, err)),
+                },
+                None => errors.push(format!("colors: unknown color \"{}\"", name)),
+            }
+        }
+
+        (config, errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use console_engine::{KeyCode, KeyModifiers};
+
+    use super::*;
+
+    fn validate(contents: &str) -> (Config, Vec<String>) {
+        Config::validate(toml::from_str(contents).unwrap())
+    }
+
+    #[test]
+    fn empty_config_uses_defaults() {
+        let (config, errors) = validate("");
+        assert!(errors.is_empty());
+        assert_eq!(config.adb_path, "adb");
+        assert_eq!(config.device_start, "/sdcard");
+        assert_eq!(config.log_level, "debug");
+    }
+
+    #[test]
+    fn valid_settings() {
+        let (config, errors) = validate(
+            r##"
+            adb_path = "/opt/adb"
+            log_level = "WARN"
+            [start]
+            device = "/data/local/tmp"
+            [keys]
+            copy = ["F6", "Ctrl+C"]
+            [colors]
+            list_fg = "#102030"
+            [openers]
+            ".PNG" = "feh"
+            "##,
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(config.adb_path, "/opt/adb");
+        assert_eq!(config.log_level, "warn");
+        assert_eq!(config.device_start, "/data/local/tmp");
+        assert_eq!(
+            config.keymap.get_keys(Action::Copy),
+            &[
+                KeyBinding {
+                    code: KeyCode::F(6),
+                    modifiers: KeyModifiers::NONE
+                },
+                KeyBinding {
+                    code: KeyCode::Char('c'),
+                    modifiers: KeyModifiers::CONTROL
+                }
+            ]
+        );
+        assert_eq!(
+            config.theme.list_fg,
+            Color::Rgb {
+                r: 0x10,
+                g: 0x20,
+                b: 0x30
+            }
+        );
+        assert_eq!(config.openers.get("png").map(String::as_str), Some("feh"));
+    }
+
+    #[test]
+    fn invalid_settings_keep_defaults() {
+        let (config, errors) = validate(
+            r#"
+            adb_path = "/opt/adb"
+            log_level = "loud"
+            [start]
+            device = "sdcard"
+            [keys]
+            copy = "Hyper+C"
+            jump = "J"
+            [colors]
+            list_fg = "blurple"
+            "#,
+        );
+        assert_eq!(errors.len(), 5, "{:?}", errors);
+        assert_eq!(config.adb_path, "/opt/adb");
+        assert_eq!(config.log_level, "debug");
+        assert_eq!(config.device_start, "/sdcard");
+        assert_eq!(
+            config.keymap.get_keys(Action::Copy),
+            &[KeyBinding::parse("F5").unwrap()]
+        );
+        assert_eq!(config.theme.list_fg, Color::Reset);
+    }
+
+    #[test]
+    fn unknown_settings_are_reported() {
+        let (config, errors) = validate(
+            r#"
+            adb_pth = "/opt/adb"
+            log_level = "info"
+            [start]
+            devise = "/data"
+            "#,
+        );
+        assert_eq!(
+            errors,
+            [
+                "unknown setting \"adb_pth\" is ignored",
+                "unknown setting \"start.devise\" is ignored"
+            ]
+        );
+        assert_eq!(config.log_level, "info");
+    }
+
+    #[test]
+    fn conflicting_keys() {
+        let (_, errors) = validate("[keys]\ncopy = \"F3\"");
+        assert_eq!(errors, ["keys: F3 is bound to both copy and view"]);
+    }
+}
@@ -0,0 +1,118 @@
+use anyhow::Result;
+use console_engine::ConsoleEngine;
+
+use crate::{
+    file_operations::{FileInfo, FileOperations},
+    modal::modal,
+};
+
+/// What to do with a file that already exists in destination
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Resolution {
+    Overwrite,
+    Skip,
+    Rename,
+    OverwriteIfNewer,
+}
+
+impl Resolution {
+    fn from_button(button: &str) -> Self {
+        match button.trim_end_matches(" all") {
+            "Overwrite" => Resolution::Overwrite,
+            "Rename" => Resolution::Rename,
+            "If newer" => Resolution::OverwriteIfNewer,
+            _ => Resolution::Skip,
+        }
+    }
+}
+
+/// Asks user how to resolve name conflicts and remembers "apply to all" choice for the rest of the batch
+pub struct ConflictResolver {
+    batch: bool,
+    apply_to_all: Option<Resolution>,
+}
+
+impl ConflictResolver {
+    /// Creates resolver. `batch` enables "apply to all" buttons
+    pub fn new(batch: bool) -> Self {
+        Self {
+            batch,
+            apply_to_all: None,
+        }
+    }
+
+    /// Resolves conflict of `source` file with existing `destination` file.
+    /// Returns destination name to write into or `None` if file must be skipped
+    pub fn resolve(
+        &mut self,
+        engine: &mut ConsoleEngine,
+        name: &str,
+        source: FileInfo,
+        destination_files: &mut dyn FileOperations,
+    ) -> Result<Option<String>> {
+        let destination = match destination_files.file_info(name)? {
+            Some(info) => info,
+            None => return Ok(Some(name.to_string())),
+        };
+
+        let resolution = match self.apply_to_all {
+            Some(resolution) => resolution,
+            None => {
+                let mut buttons = vec!["Overwrite", "Skip", "Rename", "If newer"];
+                if self.batch {
+                    buttons.extend(&["Overwrite all", "Skip all", "Rename all", "If newer all"]);
+                }
+
+                let button = modal(
+                    engine,
+                    format!(
+                        "File {} already exists\nSource:      {:>10} {}\nDestination: {:>10} {}",
+                        name,
+                        source.format_size(),
+                        source.format_modified(),
+                        destination.format_size(),
+                        destination.format_modified(),
+                    ),
+                    buttons,
+                );
+
+                let resolution = Resolution::from_button(&button);
+                if button.ends_with(" all") {
+                    self.apply_to_all = Some(resolution);
+                }
+                resolution
+            }
+        };
+
+        Ok(match resolution {
+            Resolution::Overwrite => Some(name.to_string()),
+            Resolution::Skip => None,
+            Resolution::OverwriteIfNewer if source.modified > destination.modified => {
+                Some(name.to_string())
+            }
+            Resolution::OverwriteIfNewer => None,
+            Resolution::Rename => Some(free_name(name, destination_files)?),
+        })
+    }
+}
+
+/// Finds name like `file (1).txt` that does not exist in destination
+fn free_name(name: &str, destination_files: &mut dyn FileOperations) -> Result<String> {
+    let (dir, file_name) = match name.rfind('/') {
+        Some(pos) => name.split_at(pos + 1),
+        None => ("", name),
+    };
+
+    let (stem, extension) = match file_name.rfind('.') {
+        Some(pos) if pos > 0 => file_name.split_at(pos),
+        _ => (file_name, ""),
+    };
+
+    for idx in 1.. {
+        let candidate = format!("{}{} ({}){}", dir, stem, idx, extension);
+        if destination_files.file_info(&candidate)?.is_none() {
+            return Ok(candidate);
+        }
+    }
+    unreachable!()
+}
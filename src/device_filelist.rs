@@ -1,51 +1,264 @@
-use crate::{file_operations::FileOperations, layout::Layout, listbox::ListBox};
+use crate::{
+    archive::Archive,
+    file_operations::{FileEntry, FileOperations},
+    keymap::Action,
+    layout::Layout,
+    listbox::ListBox,
+};
 use anyhow::Result;
-use console_engine::{ConsoleEngine, KeyCode};
+use std::collections::HashMap;
 
-pub struct DeviceFilelist<T: FileOperations> {
-    pub listbox: ListBox,
-    pub device_files: T,
+/// Visited directory together with cursor and scroll position in it
+struct HistoryEntry {
+    directory: String,
+    selected: usize,
+    scroll: usize,
 }
 
-impl<T: FileOperations> DeviceFilelist<T>
-where
-    T: FileOperations,
-{
-    pub fn new(layout: &Layout, mut device_files: T) -> Result<Self> {
+pub struct DeviceFilelist {
+    pub listbox: ListBox<FileEntry>,
+    pub device_files: Box<dyn FileOperations>,
+    /// Sources left when entering archive. Going up from archive root returns to them
+    parent_sources: Vec<Box<dyn FileOperations>>,
+    /// Directories to return to with Alt+Left, most recent last
+    back: Vec<HistoryEntry>,
+    /// Directories left with Alt+Left, most recent last
+    forward: Vec<HistoryEntry>,
+    /// Cursor and scroll position in directories visited during this session, keyed by source
+    /// name and directory
+    cursors: HashMap<String, (usize, usize)>,
+    /// Source key and directory entered since last `take_visit`, to record in recent directories
+    visited: Option<(String, String)>,
+}
+
+impl DeviceFilelist {
+    pub fn new(layout: &Layout, mut device_files: Box<dyn FileOperations>) -> Result<Self> {
         let mut ls = ListBox::new(layout, false);
-        ls.set_content(device_files.get_files()?);
+        ls.set_content(device_files.get_entries()?);
 
-        Ok(Self {
+        let mut filelist = Self {
             device_files,
             listbox: ls,
-        })
+            parent_sources: Vec::new(),
+            back: Vec::new(),
+            forward: Vec::new(),
+            cursors: HashMap::new(),
+            visited: None,
+        };
+        filelist.mark_visited();
+        Ok(filelist)
     }
 
-    pub fn handle_listbox(&mut self, engine: &ConsoleEngine) -> Result<()> {
-        self.listbox.handle_events(engine);
-        if self.listbox.focused {
-            if engine.is_key_pressed(KeyCode::Enter) {
-                self.device_files
-                    .change_directory_rel(self.listbox.get_selected_str().as_str());
-                let files = self.device_files.get_files()?;
-                self.listbox.set_content(files);
-            }
+    /// Runs pane action: cursor movement, opening entries and history navigation
+    pub fn handle_action(&mut self, action: Action) -> Result<()> {
+        match action {
+            Action::Open => self.open_selected()?,
+            Action::Parent => self.go_parent()?,
+            Action::Back => self.go_back()?,
+            Action::Forward => self.go_forward()?,
+            _ => self.listbox.handle_action(action),
         }
+        Ok(())
+    }
 
-        if engine.is_key_pressed(KeyCode::Tab) {
-            self.listbox.focused = !self.listbox.focused;
+    /// Enters selected directory or archive
+    fn open_selected(&mut self) -> Result<()> {
+        let selected = self.listbox.get_selected_str();
+        match self.device_files.local_path(&selected) {
+            Some(path) if !selected.ends_with('/') && Archive::is_archive(&path) => {
+                let archive = Box::new(Archive::open(path)?);
+                self.remember_cursor();
+                let parent = std::mem::replace(&mut self.device_files, archive);
+                self.parent_sources.push(parent);
+                self.clear_history();
+                self.mark_visited();
+                self.update_filelist()
+            }
+            _ => self.navigate(|files| {
+                files.change_directory_rel(selected.as_str());
+                Ok(())
+            }),
         }
+    }
 
-        if self.listbox.focused && engine.is_key_pressed(KeyCode::Backspace) {
-            let files = self.device_files.level_up_files()?;
-            self.listbox.set_content(files);
+    /// Goes to parent directory with cursor on directory it came from,
+    /// or leaves archive when at its root
+    fn go_parent(&mut self) -> Result<()> {
+        if self.at_archive_root() {
+            self.device_files = self.parent_sources.pop().unwrap();
+            self.clear_history();
+            self.mark_visited();
+            self.update_filelist()?;
+            self.recall_cursor();
+        } else {
+            let came_from = self
+                .device_files
+                .get_working_directory()
+                .trim_end_matches('/')
+                .rsplit('/')
+                .next()
+                .map(|name| format!("{}/", name));
+            self.navigate(|files| {
+                files.level_up();
+                Ok(())
+            })?;
+            if let Some(came_from) = came_from {
+                self.listbox.select(&came_from);
+            }
         }
         Ok(())
     }
 
+    /// Replaces pane file list source
+    pub fn set_source(&mut self, mut device_files: Box<dyn FileOperations>) -> Result<()> {
+        self.listbox.set_content(device_files.get_entries()?);
+        self.device_files = device_files;
+        self.parent_sources.clear();
+        self.clear_history();
+        self.mark_visited();
+        Ok(())
+    }
+
     pub fn update_filelist(&mut self) -> Result<()> {
-        let files = self.device_files.get_files()?;
+        let files = self.device_files.get_entries()?;
         self.listbox.set_content(files);
         Ok(())
     }
+
+    /// Goes to absolute `path`, remembering current directory in history
+    pub fn go_to(&mut self, path: &str) -> Result<()> {
+        self.navigate(|files| {
+            files.change_directory(path);
+            Ok(())
+        })
+    }
+
+    /// Returns to previously visited directory
+    pub fn go_back(&mut self) -> Result<()> {
+        if let Some(entry) = self.back.pop() {
+            let current = self.current_entry();
+            if let Err(err) = self.restore(&entry) {
+                self.back.push(entry);
+                return Err(err);
+            }
+            self.forward.push(current);
+        }
+        Ok(())
+    }
+
+    /// Returns to directory left with `go_back`
+    pub fn go_forward(&mut self) -> Result<()> {
+        if let Some(entry) = self.forward.pop() {
+            let current = self.current_entry();
+            if let Err(err) = self.restore(&entry) {
+                self.forward.push(entry);
+                return Err(err);
+            }
+            self.back.push(current);
+        }
+        Ok(())
+    }
+
+    /// Checks whether there is parent directory or archive to go up to
+    pub fn can_go_up(&self) -> bool {
+        self.device_files.get_working_directory() != "/" || !self.parent_sources.is_empty()
+    }
+
+    /// Checks whether going up leaves archive
+    pub fn at_archive_root(&self) -> bool {
+        self.device_files.get_working_directory() == "/" && !self.parent_sources.is_empty()
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        !self.back.is_empty()
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        !self.forward.is_empty()
+    }
+
+    /// Changes directory with `change` and records previous one in history if directory changed.
+    /// If new directory can not be listed, like when access is denied, previous one is kept
+    fn navigate(
+        &mut self,
+        change: impl FnOnce(&mut dyn FileOperations) -> Result<()>,
+    ) -> Result<()> {
+        let entry = self.current_entry();
+        self.remember_cursor();
+        if let Err(err) = change(self.device_files.as_mut()).and_then(|_| self.update_filelist()) {
+            self.device_files.change_directory(&entry.directory);
+            return Err(err);
+        }
+
+        if self.device_files.get_working_directory() != entry.directory {
+            self.back.push(entry);
+            self.forward.clear();
+            self.mark_visited();
+        }
+        self.recall_cursor();
+        Ok(())
+    }
+
+    fn cursor_key(&self) -> String {
+        format!(
+            "{}:{}",
+            self.device_files.get_source_name(),
+            self.device_files.get_working_directory()
+        )
+    }
+
+    /// Remembers cursor and scroll position in current directory, to restore it when returning there
+    fn remember_cursor(&mut self) {
+        let key = self.cursor_key();
+        let position = (self.listbox.get_selected_idx(), self.listbox.get_scroll());
+        self.cursors.insert(key, position);
+    }
+
+    /// Restores cursor and scroll position remembered for current directory
+    fn recall_cursor(&mut self) {
+        if let Some(&(selected, scroll)) = self.cursors.get(&self.cursor_key()) {
+            self.listbox.set_view(selected, scroll);
+        }
+    }
+
+    fn current_entry(&self) -> HistoryEntry {
+        HistoryEntry {
+            directory: self.device_files.get_working_directory().to_string(),
+            selected: self.listbox.get_selected_idx(),
+            scroll: self.listbox.get_scroll(),
+        }
+    }
+
+    /// Goes to history `entry`. If it can not be listed, current directory is kept
+    fn restore(&mut self, entry: &HistoryEntry) -> Result<()> {
+        let previous = self.device_files.get_working_directory().to_string();
+        self.remember_cursor();
+        self.device_files.change_directory(&entry.directory);
+        if let Err(err) = self.update_filelist() {
+            self.device_files.change_directory(&previous);
+            return Err(err);
+        }
+        self.mark_visited();
+        self.listbox.set_view(entry.selected, entry.scroll);
+        Ok(())
+    }
+
+    /// Marks current directory as entered, if source keeps recent directories
+    fn mark_visited(&mut self) {
+        self.visited = self
+            .device_files
+            .get_source_key()
+            .map(|key| (key, self.device_files.get_working_directory().to_string()));
+    }
+
+    /// Takes directory entered since last call, to record it in recent directories
+    pub fn take_visit(&mut self) -> Option<(String, String)> {
+        self.visited.take()
+    }
+
+    /// Forgets history, since its directories belong to another source
+    fn clear_history(&mut self) {
+        self.back.clear();
+        self.forward.clear();
+    }
 }
@@ -0,0 +1,238 @@
+use std::{
+    env, fs,
+    io::stdout,
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus, Stdio},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Result};
+use console_engine::{
+    crossterm::{cursor, event, execute, terminal},
+    ConsoleEngine,
+};
+
+use crate::{config, file_operations::FileOperations, transfer};
+
+/// Gives terminal back to `command` while it runs, like shell does for foreground jobs.
+/// Screen is redrawn completely afterwards
+pub fn run_suspended(engine: &mut ConsoleEngine, command: &mut Command) -> Result<ExitStatus> {
+    execute!(
+        stdout(),
+        cursor::Show,
+        event::DisableMouseCapture,
+        terminal::LeaveAlternateScreen
+    )?;
+    terminal::disable_raw_mode()?;
+
+    let status = command.status();
+
+    terminal::enable_raw_mode()?;
+    execute!(
+        stdout(),
+        terminal::EnterAlternateScreen,
+        cursor::Hide,
+        event::EnableMouseCapture
+    )?;
+    engine.request_full_draw();
+
+    Ok(status?)
+}
+
+/// Builds command from command line like `mpv --really-quiet`, appending `path` as last argument
+fn command_with_path(command_line: &str, path: &Path) -> Command {
+    let mut words = command_line.split_whitespace();
+    let mut command = Command::new(words.next().unwrap_or_default());
+    command.args(words).arg(path);
+    command
+}
+
+/// Gets command of user's editor from `$VISUAL` or `$EDITOR`, falling back to `vi`.
+/// Variable may contain arguments, like `code --wait`
+fn editor_command(path: &Path) -> Command {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .ok()
+        .filter(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+    command_with_path(&editor, path)
+}
+
+fn run_editor(engine: &mut ConsoleEngine, path: &Path) -> Result<()> {
+    let mut command = editor_command(path);
+    let program = command.get_program().to_string_lossy().to_string();
+    let status = run_suspended(engine, &mut command)
+        .map_err(|err| anyhow!("can not run editor {}: {}", program, err))?;
+    if !status.success() {
+        log::warn!("Editor {} exited with {}", program, status);
+    }
+    Ok(())
+}
+
+/// Gets size and modification time, to tell whether editor saved file
+fn file_state(path: &Path) -> Result<(u64, SystemTime)> {
+    let metadata = fs::metadata(path)?;
+    Ok((metadata.len(), metadata.modified()?))
+}
+
+/// Gets directory for copies of files being edited
+fn edit_dir() -> PathBuf {
+    env::temp_dir().join(format!("adbexplorer-edit-{}", std::process::id()))
+}
+
+/// Edits file `name` of `files` working directory in user's editor. Local files are edited in
+/// place. Other files are pulled to temporary directory and pushed back if editor changed them.
+/// Files that can not be written are refused before editor starts
+pub fn edit(engine: &mut ConsoleEngine, files: &mut dyn FileOperations, name: &str) -> Result<()> {
+    if files.is_read_only() {
+        return Err(anyhow!(
+            "{} is read-only, {} can not be edited",
+            files.get_source_name(),
+            name
+        ));
+    }
+    if let Some(path) = files.local_path(name) {
+        return run_editor(engine, &path);
+    }
+
+    let dir = edit_dir();
+    fs::create_dir_all(&dir)?;
+    // Keeps file name, so editor can detect file type
+    let file_name = name.rsplit('/').next().unwrap_or(name);
+    let path = dir.join(file_name);
+
+    if !transfer::pull(engine, files, name, &path)? {
+        fs::remove_file(&path)?;
+        return Ok(());
+    }
+    let before = file_state(&path)?;
+    run_editor(engine, &path)?;
+
+    if file_state(&path)? == before {
+        log::info!("{} was not changed", name);
+    } else {
+        // Copy is kept if push did not complete, so edits are not lost
+        match transfer::push(engine, &path, files, name) {
+            Ok(true) => log::info!("Pushed edited {}", name),
+            Ok(false) => {
+                return Err(anyhow!(
+                    "Pushing was cancelled, {} was not changed. Edited copy is kept in {}",
+                    name,
+                    path.display()
+                ))
+            }
+            Err(err) => {
+                return Err(anyhow!(
+                    "{}. Edited copy is kept in {}",
+                    err,
+                    path.display()
+                ))
+            }
+        }
+    }
+
+    fs::remove_file(&path)?;
+    Ok(())
+}
+
+/// Gets path in cache directory for copy of file `name` of `files` working directory.
+/// Path mirrors source and full path of file, so files with same name do not clash
+fn cache_path(files: &dyn FileOperations, name: &str) -> PathBuf {
+    let source = files
+        .get_source_key()
+        .unwrap_or_else(|| files.get_source_name())
+        .chars()
+        .map(|char| {
+            if char.is_alphanumeric() || "-_.".contains(char) {
+                char
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    let full_path = format!(
+        "{}/{}",
+        files.get_working_directory().trim_end_matches('/'),
+        name
+    );
+
+    full_path
+        .split('/')
+        .filter(|part| !part.is_empty() && *part != "..")
+        .fold(config::cache_dir().join(source), |path, part| {
+            path.join(part)
+        })
+}
+
+/// Gets local path of file `name` of `files` working directory. Files of other sources are pulled
+/// to cache directory first, showing progress window. Cached copy is reused while file keeps its
+/// size and modification time. Returns `None` if pulling was cancelled
+pub fn local_copy(
+    engine: &mut ConsoleEngine,
+    files: &mut dyn FileOperations,
+    name: &str,
+) -> Result<Option<PathBuf>> {
+    if let Some(path) = files.local_path(name) {
+        return Ok(Some(path));
+    }
+
+    let path = cache_path(files, name);
+    let info = files.file_info(name)?;
+    let modified = info.map(|info| UNIX_EPOCH + Duration::from_secs(info.modified));
+    let cached = fs::metadata(&path)
+        .ok()
+        .map(|metadata| (metadata.len(), metadata.modified().ok()));
+    if info.is_some() && cached == info.map(|info| (info.size, modified)) {
+        log::info!("Using cached copy of {}", name);
+        return Ok(Some(path));
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if !transfer::pull(engine, files, name, &path)? {
+        fs::remove_file(&path)?;
+        return Ok(None);
+    }
+    if let Some(modified) = modified {
+        fs::File::options()
+            .write(true)
+            .open(&path)?
+            .set_modified(modified)?;
+    }
+    Ok(Some(path))
+}
+
+/// Gets opener command configured for extension of `name`, or system handler
+fn opener(name: &str) -> &'static str {
+    let config = config::get();
+    name.rsplit_once('.')
+        .and_then(|(_, extension)| config.openers.get(&extension.to_lowercase()))
+        .unwrap_or(&config.opener)
+}
+
+/// Opens file `name` of `files` working directory with its opener, without waiting for it to close
+pub fn launch(
+    engine: &mut ConsoleEngine,
+    files: &mut dyn FileOperations,
+    name: &str,
+) -> Result<()> {
+    let path = match local_copy(engine, files, name)? {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+
+    let opener = opener(name);
+    log::info!("Opening {} with {}", path.display(), opener);
+    let mut child = command_with_path(opener, &path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|err| anyhow!("can not run {}: {}", opener, err))?;
+
+    // Reaps opener once it exits, so it does not stay as zombie process
+    thread::spawn(move || child.wait());
+    Ok(())
+}
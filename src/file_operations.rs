@@ -1,27 +1,411 @@
-use std::{env, fs};
-use anyhow::Result;
+use crate::{config, listbox::ListBoxItem};
+use anyhow::{anyhow, Result};
+use console_engine::Color;
+use std::{
+    env,
+    fs::{self, File, OpenOptions},
+    io::{self, ErrorKind, PipeReader, PipeWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    thread::{self, JoinHandle},
+    time::UNIX_EPOCH,
+};
 
 pub struct Local {
     working_directory: String,
 }
 
+/// Formats size in human readable units
+pub fn format_size(size: u64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut scaled = size as f64;
+    let mut unit = 0;
+
+    while scaled >= 1024.0 && unit < units.len() - 1 {
+        scaled /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", size, units[0])
+    } else {
+        format!("{:.1} {}", scaled, units[unit])
+    }
+}
+
+/// Size and modification time of a file
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FileInfo {
+    pub size: u64,
+    /// Modification time in seconds since unix epoch
+    pub modified: u64,
+}
+
+impl FileInfo {
+    /// Formats file size in human readable units
+    pub fn format_size(&self) -> String {
+        format_size(self.size)
+    }
+
+    /// Formats modification time as `YYYY-MM-DD HH:MM:SS` in UTC
+    pub fn format_modified(&self) -> String {
+        let days = (self.modified / 86400) as i64;
+        let secs = self.modified % 86400;
+
+        // civil from days algorithm by Howard Hinnant
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z - era * 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        format!(
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            year,
+            month,
+            day,
+            secs / 3600,
+            secs % 3600 / 60,
+            secs % 60
+        )
+    }
+}
+
+/// Mounted storage volume with its space usage
+#[derive(Clone, Debug, PartialEq)]
+pub struct Volume {
+    /// Human readable name, like "Internal storage"
+    pub name: String,
+    /// Absolute path volume is mounted at
+    pub path: String,
+    pub total: u64,
+    pub free: u64,
+}
+
+/// Type of directory entry, shown with its own color and glyph
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileKind {
+    Directory,
+    Symlink,
+    /// Symlink whose target does not exist
+    BrokenSymlink,
+    Executable,
+    Apk,
+    Image,
+    File,
+}
+
+/// Entry of directory listing. Name follows `get_files` format, directories end with `/`
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileEntry {
+    pub name: String,
+    pub kind: FileKind,
+}
+
+impl FileEntry {
+    pub fn new(name: String, kind: FileKind) -> Self {
+        Self { name, kind }
+    }
+
+    /// Creates entry with type guessed from name only: trailing `/` for directories and extension
+    /// for APKs and images
+    pub fn from_name(name: String) -> Self {
+        let kind = if name.ends_with('/') {
+            FileKind::Directory
+        } else {
+            Self::kind_by_extension(&name)
+        };
+        Self { name, kind }
+    }
+
+    /// Gets kind of regular file by its extension
+    pub fn kind_by_extension(name: &str) -> FileKind {
+        let extension = Path::new(name)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        match extension.as_str() {
+            "apk" => FileKind::Apk,
+            "png" | "jpg" | "jpeg" | "gif" | "webp" | "bmp" | "heic" => FileKind::Image,
+            _ => FileKind::File,
+        }
+    }
+}
+
+impl ListBoxItem for FileEntry {
+    fn get_entry(&self) -> String {
+        self.name.clone()
+    }
+
+    fn bg_color(&self) -> Color {
+        Color::Reset
+    }
+
+    fn fg_color(&self) -> Color {
+        let theme = &config::get().theme;
+        match self.kind {
+            FileKind::Directory => theme.file_directory_fg,
+            FileKind::Symlink => theme.file_symlink_fg,
+            FileKind::BrokenSymlink => theme.file_broken_symlink_fg,
+            FileKind::Executable => theme.file_executable_fg,
+            FileKind::Apk => theme.file_apk_fg,
+            FileKind::Image => theme.file_image_fg,
+            FileKind::File => theme.list_fg,
+        }
+    }
+
+    fn glyph(&self) -> Option<char> {
+        Some(match self.kind {
+            FileKind::Directory => '▸',
+            FileKind::Symlink => '→',
+            FileKind::BrokenSymlink => '✗',
+            FileKind::Executable => '*',
+            FileKind::Apk => '◆',
+            FileKind::Image => '▣',
+            FileKind::File => ' ',
+        })
+    }
+}
+
+/// Contents of file being read from file list
+pub trait FileReader: Read {
+    /// Finishes reading and checks that file was read successfully
+    fn finish(self: Box<Self>) -> Result<()>;
+    /// Aborts reading
+    fn cancel(self: Box<Self>) -> Result<()>;
+}
+
+/// Contents of file being written into file list
+pub trait FileWriter: Write {
+    /// Finishes writing and checks that file was written successfully
+    fn finish(self: Box<Self>) -> Result<()>;
+    /// Aborts writing. Written data may be left in file
+    fn cancel(self: Box<Self>) -> Result<()>;
+}
+
+/// Tar archive being streamed out of file list
+pub trait TarReader: Read {
+    /// Finishes streaming. Returns files put into archive
+    fn finish(self: Box<Self>) -> Result<Vec<String>>;
+    /// Aborts streaming
+    fn cancel(self: Box<Self>) -> Result<()>;
+}
+
+/// Tar archive being extracted into file list
+pub trait TarWriter: Write {
+    /// Finishes extraction. Returns extracted files
+    fn finish(self: Box<Self>) -> Result<Vec<String>>;
+    /// Aborts extraction. Already extracted files are left in place
+    fn cancel(self: Box<Self>) -> Result<()>;
+}
+
 pub trait FileOperations {
+    /// Gets human readable name of file list source
+    fn get_source_name(&self) -> String;
+    /// Gets stable identifier of source to store bookmarks under.
+    /// Sources without one, like archives, have no bookmarks
+    fn get_source_key(&self) -> Option<String> {
+        None
+    }
     fn get_files(&mut self) -> Result<Vec<String>>;
+    /// Lists working directory like `get_files`, with type of each entry
+    fn get_entries(&mut self) -> Result<Vec<FileEntry>> {
+        Ok(self
+            .get_files()?
+            .into_iter()
+            .map(FileEntry::from_name)
+            .collect())
+    }
     fn change_directory_rel(&mut self, path: &str);
-    fn level_up_files(&mut self) -> Result<Vec<String>>;
+    /// Changes working directory to absolute `path`, if it is a directory
+    fn change_directory(&mut self, path: &str);
+    /// Changes working directory to its parent, without listing it
+    fn level_up(&mut self);
     fn is_directory(&self, path: String) -> bool;
     fn get_working_directory(&self) -> &str;
+    /// Gets size and modification time of file relative to working directory. Returns `None` if file
+    /// does not exist, and error if it can not be checked, like when access is denied
+    fn file_info(&mut self, path: &str) -> Result<Option<FileInfo>>;
+    /// Recursively collects all files inside `path`, relative to working directory.
+    /// If `path` is a file returns only it
+    fn walk(&mut self, path: &str) -> Result<Vec<String>>;
+    /// Opens file relative to working directory for reading, starting from `offset` byte
+    fn open_read(&mut self, path: &str, offset: u64) -> Result<Box<dyn FileReader>>;
+    /// Reads up to `len` bytes of file relative to working directory starting from `offset` byte,
+    /// without reading the rest of file
+    fn read_range(&mut self, path: &str, offset: u64, len: u64) -> Result<Vec<u8>> {
+        let mut reader = self.open_read(path, offset)?;
+        let mut data = Vec::new();
+        reader.by_ref().take(len).read_to_end(&mut data)?;
+        reader.cancel()?;
+        Ok(data)
+    }
+    /// Opens file relative to working directory for writing, creating missing parent directories.
+    /// If `append` is set, data is written to the end of existing file
+    fn open_write(&mut self, path: &str, append: bool) -> Result<Box<dyn FileWriter>>;
+    /// Renames file relative to working directory, replacing existing one
+    fn rename(&mut self, from: &str, to: &str) -> Result<()>;
+    /// Copies contents of file `from` over existing file `to`, both relative to working directory,
+    /// so `to` keeps its permissions and owner
+    fn overwrite(&mut self, from: &str, to: &str) -> Result<()> {
+        let mut input = self.open_read(from, 0)?;
+        let mut output = self.open_write(to, false)?;
+        io::copy(&mut input, &mut output)?;
+        input.finish()?;
+        output.finish()
+    }
+    /// Removes file relative to working directory
+    fn remove(&mut self, path: &str) -> Result<()>;
+    /// Checks whether files can not be written, so copying into file list is refused upfront
+    fn is_read_only(&self) -> bool {
+        false
+    }
+    /// Gets path of file relative to working directory in local filesystem, if file list is local
+    fn local_path(&self, _path: &str) -> Option<PathBuf> {
+        None
+    }
+    /// Streams directory relative to working directory as tar archive
+    fn open_tar_read(&mut self, _path: &str) -> Result<Box<dyn TarReader>> {
+        Err(anyhow!(
+            "{} can not stream tar archives",
+            self.get_source_name()
+        ))
+    }
+    /// Extracts tar archive into working directory
+    fn open_tar_write(&mut self) -> Result<Box<dyn TarWriter>> {
+        Err(anyhow!(
+            "{} can not extract tar archives",
+            self.get_source_name()
+        ))
+    }
+    /// Lists mounted storage volumes, like internal storage and SD cards
+    fn volumes(&mut self) -> Result<Vec<Volume>> {
+        Ok(Vec::new())
+    }
+    /// Checks whether `switch_privilege` can be used
+    fn has_privilege_modes(&self) -> bool {
+        false
+    }
+    /// Switches to next privilege mode, like running commands as root.
+    /// Falls back to unprivileged mode if privilege can not be obtained
+    fn switch_privilege(&mut self) -> Result<()> {
+        Err(anyhow!("{} has no privilege modes", self.get_source_name()))
+    }
+}
+
+/// Tar archive streamed through pipe from or to background thread
+struct PipeTar<P> {
+    pipe: P,
+    thread: JoinHandle<Result<Vec<String>>>,
+}
+
+impl<P> PipeTar<P> {
+    fn join(self) -> Result<Vec<String>> {
+        drop(self.pipe);
+        self.thread
+            .join()
+            .map_err(|_| anyhow!("Tar thread panicked"))?
+    }
+}
+
+impl Read for PipeTar<PipeReader> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.pipe.read(buf)
+    }
+}
+
+impl Write for PipeTar<PipeWriter> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.pipe.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.pipe.flush()
+    }
+}
+
+impl TarReader for PipeTar<PipeReader> {
+    fn finish(self: Box<Self>) -> Result<Vec<String>> {
+        self.join()
+    }
+
+    fn cancel(self: Box<Self>) -> Result<()> {
+        let _ = self.join();
+        Ok(())
+    }
+}
+
+impl TarWriter for PipeTar<PipeWriter> {
+    fn finish(self: Box<Self>) -> Result<Vec<String>> {
+        self.join()
+    }
+
+    fn cancel(self: Box<Self>) -> Result<()> {
+        let _ = self.join();
+        Ok(())
+    }
+}
+
+impl FileReader for File {
+    fn finish(self: Box<Self>) -> Result<()> {
+        Ok(())
+    }
+
+    fn cancel(self: Box<Self>) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl FileWriter for File {
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        self.flush()?;
+        Ok(())
+    }
+
+    fn cancel(self: Box<Self>) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &fs::Metadata) -> bool {
+    false
 }
 
 impl Local {
     pub fn new() -> Result<Local> {
-        Ok(Local {
-            working_directory: env::current_dir()?.to_string_lossy().into(),
-        })
+        let working_directory = match &config::get().local_start {
+            Some(start) => start.clone(),
+            None => env::current_dir()?.to_string_lossy().into(),
+        };
+        Ok(Local { working_directory })
+    }
+
+    /// Gets full path of file relative to working directory
+    pub fn get_path(&self, path: &str) -> PathBuf {
+        Path::new(&self.working_directory).join(path.trim_end_matches('/'))
     }
 }
 
 impl FileOperations for Local {
+    fn get_source_name(&self) -> String {
+        "Local".to_string()
+    }
+
+    fn get_source_key(&self) -> Option<String> {
+        Some("local".to_string())
+    }
+
     fn get_files(&mut self) -> Result<Vec<String>> {
         log::info!("Going to: {}", self.get_working_directory());
         let paths = fs::read_dir(self.get_working_directory())?;
@@ -41,24 +425,47 @@ impl FileOperations for Local {
         Ok(files)
     }
 
+    fn get_entries(&mut self) -> Result<Vec<FileEntry>> {
+        let mut entries = Vec::new();
+
+        for name in self.get_files()? {
+            let path = self.get_path(&name);
+            let kind = match (fs::symlink_metadata(&path), fs::metadata(&path)) {
+                (Ok(link), Err(_)) if link.file_type().is_symlink() => FileKind::BrokenSymlink,
+                (Ok(link), _) if link.file_type().is_symlink() => FileKind::Symlink,
+                (_, Ok(metadata)) if metadata.is_dir() => FileKind::Directory,
+                (_, Ok(metadata)) if is_executable(&metadata) => FileKind::Executable,
+                _ => FileEntry::kind_by_extension(&name),
+            };
+            entries.push(FileEntry::new(name, kind));
+        }
+
+        Ok(entries)
+    }
+
     // TODO: Make these functions work on Non-unix systems e.g Windows
     fn change_directory_rel(&mut self, path: &str) {
-        let concat_dir = &format!("{}/{}", self.working_directory, path);
-        if self.is_directory(concat_dir.to_string()) {
-            self.working_directory = concat_dir.to_string();
+        let concat_dir = self.get_path(path).to_string_lossy().to_string();
+        if self.is_directory(concat_dir.clone()) {
+            self.working_directory = concat_dir;
         }
     }
 
-    fn level_up_files(&mut self) -> Result<Vec<String>> {
-        let mut splited_path = self.working_directory.split("/").collect::<Vec<&str>>();
-        if splited_path.len() > 2 {
-            splited_path.remove(splited_path.len() - 1);
-            log::info!("Len: {}", splited_path.len());
-            self.working_directory = splited_path.join("/");
-        } else {
-            self.working_directory = "/".to_string();
+    fn change_directory(&mut self, path: &str) {
+        if self.is_directory(path.to_string()) {
+            let trimmed = path.trim_end_matches('/');
+            self.working_directory = if trimmed.is_empty() {
+                "/".to_string()
+            } else {
+                trimmed.to_string()
+            };
+        }
+    }
+
+    fn level_up(&mut self) {
+        if let Some(parent) = Path::new(&self.working_directory).parent() {
+            self.working_directory = parent.to_string_lossy().to_string();
         }
-        self.get_files()
     }
 
     fn is_directory(&self, path: String) -> bool {
@@ -72,4 +479,121 @@ impl FileOperations for Local {
     fn get_working_directory(&self) -> &str {
         self.working_directory.as_str()
     }
+
+    fn file_info(&mut self, path: &str) -> Result<Option<FileInfo>> {
+        let metadata = match fs::metadata(self.get_path(path)) {
+            Ok(metadata) => metadata,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+
+        Ok(Some(FileInfo {
+            size: metadata.len(),
+            modified,
+        }))
+    }
+
+    fn walk(&mut self, path: &str) -> Result<Vec<String>> {
+        let path = path.trim_end_matches('/');
+        let full_path = self.get_path(path);
+        let mut files = Vec::new();
+
+        if !full_path.is_dir() {
+            files.push(path.to_string());
+            return Ok(files);
+        }
+
+        for entry in fs::read_dir(full_path)? {
+            let entry = entry?;
+            let rel_path = format!("{}/{}", path, entry.file_name().to_string_lossy());
+            files.extend(self.walk(&rel_path)?);
+        }
+
+        Ok(files)
+    }
+
+    fn open_read(&mut self, path: &str, offset: u64) -> Result<Box<dyn FileReader>> {
+        let mut file = File::open(self.get_path(path))?;
+        file.seek(SeekFrom::Start(offset))?;
+        Ok(Box::new(file))
+    }
+
+    fn open_write(&mut self, path: &str, append: bool) -> Result<Box<dyn FileWriter>> {
+        let full_path = self.get_path(path);
+        if let Some(parent) = full_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(append)
+            .write(true)
+            .truncate(!append)
+            .open(full_path)?;
+        Ok(Box::new(file))
+    }
+
+    fn rename(&mut self, from: &str, to: &str) -> Result<()> {
+        fs::rename(self.get_path(from), self.get_path(to))?;
+        Ok(())
+    }
+
+    fn remove(&mut self, path: &str) -> Result<()> {
+        fs::remove_file(self.get_path(path))?;
+        Ok(())
+    }
+
+    fn local_path(&self, path: &str) -> Option<PathBuf> {
+        Some(self.get_path(path))
+    }
+
+    fn open_tar_read(&mut self, path: &str) -> Result<Box<dyn TarReader>> {
+        let files = self.walk(path)?;
+        let paths = files
+            .iter()
+            .map(|file| self.get_path(file))
+            .collect::<Vec<PathBuf>>();
+        let (pipe, writer) = std::io::pipe()?;
+
+        let thread = thread::spawn(move || {
+            let mut builder = tar::Builder::new(writer);
+            for (file, path) in files.iter().zip(paths) {
+                builder.append_path_with_name(path, file)?;
+            }
+            builder.finish()?;
+            Ok(files)
+        });
+
+        Ok(Box::new(PipeTar { pipe, thread }))
+    }
+
+    fn open_tar_write(&mut self) -> Result<Box<dyn TarWriter>> {
+        let (reader, pipe) = std::io::pipe()?;
+        let destination = PathBuf::from(&self.working_directory);
+
+        let thread = thread::spawn(move || {
+            let mut archive = tar::Archive::new(reader);
+            let mut files = Vec::new();
+            for entry in archive.entries()? {
+                let mut entry = entry?;
+                let name = entry.path()?.to_string_lossy().to_string();
+                if entry.unpack_in(&destination)? {
+                    files.push(
+                        name.trim_start_matches("./")
+                            .trim_end_matches('/')
+                            .to_string(),
+                    );
+                }
+            }
+            Ok(files)
+        });
+
+        Ok(Box::new(PipeTar { pipe, thread }))
+    }
 }
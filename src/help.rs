@@ -0,0 +1,168 @@
+use console_engine::{ConsoleEngine, KeyCode};
+
+use crate::{
+    config, hint_bar::Hint, keymap::Action, layout::Layout, menu::popup, text_input::TextInput,
+};
+
+/// Gets keys of `action` joined with commas
+fn keys(action: Action) -> String {
+    config::get()
+        .keymap
+        .get_keys(action)
+        .iter()
+        .map(|key| key.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Builds help text: every bound action with its keys, then short feature overview
+fn help_lines() -> Vec<String> {
+    let mut lines = vec!["Keys".to_string()];
+    for &action in Action::ALL {
+        let keys = keys(action);
+        if !keys.is_empty() {
+            lines.push(format!("  {:<20} {}", keys, action.description()));
+        }
+    }
+
+    lines.push(String::new());
+    lines.push("Features".to_string());
+    lines.extend([
+        format!(
+            "  {} shows local filesystem, device or app sandbox in focused pane",
+            keys(Action::SelectSource)
+        ),
+        format!(
+            "  Zip and tar archives open like directories with {}, {} at their root leaves them",
+            keys(Action::Open),
+            keys(Action::Parent)
+        ),
+        format!(
+            "  {} copies directories as single tar stream, shown as [tar] in status bar",
+            keys(Action::ToggleTarMode)
+        ),
+        format!(
+            "  {} switches device access between shell, su and adb root",
+            keys(Action::SwitchPrivilege)
+        ),
+        format!(
+            "  {} and {} walk through directories visited in a pane",
+            keys(Action::Back),
+            keys(Action::Forward)
+        ),
+        format!(
+            "  {} previews selected file in place of the other pane: images, text or hex dump",
+            keys(Action::Preview)
+        ),
+        format!(
+            "  {} views file in full screen: / and ? search, : goes to line, f follows growing file",
+            keys(Action::View)
+        ),
+        format!(
+            "  {} edits file in $EDITOR, device files are pulled and pushed back when saved",
+            keys(Action::Edit)
+        ),
+        format!(
+            "  {} opens file with system handler or opener set for its extension",
+            keys(Action::Launch)
+        ),
+        "  Entries of the bottom hint bar can be clicked".to_string(),
+        "  Mouse click selects entry, double click opens it, wheel scrolls".to_string(),
+        "  Dragging entry to the other pane copies it".to_string(),
+        format!(
+            "  Keys and colors are set in {}",
+            config::config_path().display()
+        ),
+    ]);
+    lines
+}
+
+/// Computes help window position and size from the terminal size
+fn recompute_sizes(ew: u32, eh: u32) -> Layout {
+    let w = 100.min(ew);
+    // Leaves last row for hint bar
+    let h = eh.saturating_sub(1).max(5).min(eh);
+
+    Layout {
+        x: (ew as i32 - w as i32) / 2,
+        y: 0,
+        w: w as i32,
+        h: h as i32,
+    }
+}
+
+/// Shows scrollable help window with all keybindings until user closes it.
+/// Typing filters lines containing typed text
+pub fn help(engine: &mut ConsoleEngine) {
+    let keymap = &config::get().keymap;
+    let lines = help_lines();
+    let mut search = TextInput::new();
+    let mut scroll = 0usize;
+    let hints = [
+        Hint::new("Esc", "Close"),
+        Hint::info("Up/Down", "Scroll"),
+        Hint::info("Type", "Search"),
+    ];
+
+    popup(engine, "Help", &hints, recompute_sizes, |engine, frame| {
+        if engine.is_key_pressed(KeyCode::Esc)
+            || keymap.is_pressed(engine, Action::Help)
+            || frame.clicked == Some(0)
+        {
+            return Some(());
+        }
+
+        if search.handle_events(engine) {
+            scroll = 0;
+        }
+        let query = search.value.to_lowercase();
+        let visible = lines
+            .iter()
+            .filter(|line| line.to_lowercase().contains(&query))
+            .collect::<Vec<&String>>();
+
+        // Border and search line take 3 rows, at least one line is scrolled by page keys
+        let page_h = (frame.layout.h as usize).saturating_sub(3).max(1);
+        let max_scroll = visible.len().saturating_sub(page_h);
+        if keymap.is_pressed(engine, Action::CursorDown) {
+            scroll += 1;
+        }
+        if keymap.is_pressed(engine, Action::CursorUp) {
+            scroll = scroll.saturating_sub(1);
+        }
+        if keymap.is_pressed(engine, Action::PageDown) {
+            scroll += page_h;
+        }
+        if keymap.is_pressed(engine, Action::PageUp) {
+            scroll = scroll.saturating_sub(page_h);
+        }
+        if keymap.is_pressed(engine, Action::Home) {
+            scroll = 0;
+        }
+        if keymap.is_pressed(engine, Action::End) {
+            scroll = max_scroll;
+        }
+        scroll = scroll.min(max_scroll);
+
+        let width = frame.layout.w as usize;
+        let position = format!(
+            "{}-{}/{}",
+            (scroll + 1).min(visible.len()),
+            (scroll + page_h).min(visible.len()),
+            visible.len()
+        );
+        frame
+            .screen
+            .print(width as i32 - position.len() as i32 - 2, 0, &position);
+        frame.screen.print(2, 1, "Search:");
+        search.draw(frame.screen, 10, 1, width.saturating_sub(12) as u32);
+        for (row, line) in visible.iter().skip(scroll).take(page_h).enumerate() {
+            let line = line
+                .chars()
+                .take(width.saturating_sub(2))
+                .collect::<String>();
+            frame.screen.print(1, row as i32 + 2, &line);
+        }
+        None
+    })
+}
@@ -0,0 +1,127 @@
+use console_engine::{pixel, screen::Screen, ConsoleEngine, MouseButton};
+
+use crate::{config, keymap::Action};
+
+/// Key and what it does in current context
+#[derive(Clone)]
+pub struct Hint {
+    pub key: String,
+    pub label: String,
+    /// Hints that only describe keys, like `1-N`, do nothing when clicked
+    pub clickable: bool,
+}
+
+impl Hint {
+    pub fn new<K: Into<String>, L: Into<String>>(key: K, label: L) -> Self {
+        Self {
+            key: key.into(),
+            label: label.into(),
+            clickable: true,
+        }
+    }
+
+    /// Creates hint that is not clickable, for keys that have no single action
+    pub fn info<K: Into<String>, L: Into<String>>(key: K, label: L) -> Self {
+        Self {
+            clickable: false,
+            ..Self::new(key, label)
+        }
+    }
+
+    /// Creates hint with first key bound to `action`, or `None` if action has no keys
+    pub fn for_action(action: Action, label: &str) -> Option<Self> {
+        config::get()
+            .keymap
+            .get_keys(action)
+            .first()
+            .map(|key| Self::new(key.to_string(), label))
+    }
+}
+
+/// Bottom line listing available keys in Midnight Commander style: key followed by highlighted label
+pub struct HintBar {
+    hints: Vec<Hint>,
+    /// Start and end columns of each drawn hint, for mouse clicks
+    columns: Vec<(i32, i32)>,
+    screen: Screen,
+    /// Y position of the bar
+    pub y: u32,
+}
+
+impl HintBar {
+    pub fn new(engine: &ConsoleEngine) -> Self {
+        Self {
+            hints: Vec::new(),
+            columns: Vec::new(),
+            screen: Screen::new(engine.get_width(), 1),
+            y: engine.get_height() - 1,
+        }
+    }
+
+    pub fn set_hints(&mut self, hints: Vec<Hint>) {
+        self.hints = hints;
+    }
+
+    /// Draws hints that fit into bar width
+    pub fn draw(&mut self) -> &Screen {
+        let theme = &config::get().theme;
+        let width = self.screen.get_width() as i32;
+        let mut x = 0;
+
+        self.screen.fill(pixel::pxl(' '));
+        self.columns.clear();
+        for hint in &self.hints {
+            let key_w = hint.key.chars().count() as i32;
+            let label = format!("{} ", hint.label);
+            let end = x + key_w + label.chars().count() as i32;
+            if end > width {
+                break;
+            }
+
+            self.screen
+                .print_fbg(x, 0, &hint.key, theme.hint_key_fg, theme.hint_key_bg);
+            self.screen.print_fbg(
+                x + key_w,
+                0,
+                &label,
+                theme.hint_label_fg,
+                theme.hint_label_bg,
+            );
+            self.columns.push((x, end));
+            x = end + 1;
+        }
+        &self.screen
+    }
+
+    /// Gets index of hint clicked with left mouse button during current frame
+    pub fn clicked(&self, engine: &ConsoleEngine) -> Option<usize> {
+        let (x, y) = engine.get_mouse_press(MouseButton::Left)?;
+        self.hint_at(x, y)
+    }
+
+    /// Gets index of clickable hint drawn at terminal position
+    pub fn hint_at(&self, x: u32, y: u32) -> Option<usize> {
+        if y != self.y {
+            return None;
+        }
+        self.columns
+            .iter()
+            .position(|&(start, end)| (start..end).contains(&(x as i32)))
+            .filter(|&idx| self.hints[idx].clickable)
+    }
+
+    /// Resizes bar
+    pub fn resize(&mut self, w: u16, h: u16) {
+        self.screen.clear();
+        self.screen.resize(w as u32, 1);
+        self.y = h as u32 - 1;
+    }
+}
+
+/// Draws hint bar of popup at the bottom of terminal. Returns index of clicked hint
+pub fn draw_popup_hints(engine: &mut ConsoleEngine, hints: &[Hint]) -> Option<usize> {
+    let mut bar = HintBar::new(engine);
+    bar.set_hints(hints.to_vec());
+    engine.print_screen(0, bar.y as i32, bar.draw());
+    bar.clicked(engine)
+}
@@ -0,0 +1,462 @@
+use std::{collections::BTreeMap, fmt};
+
+use anyhow::{anyhow, Result};
+use console_engine::{ConsoleEngine, KeyCode, KeyModifiers};
+
+/// User action that can be bound to keys in config file `[keys]` table
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Action {
+    Quit,
+    SwitchPane,
+    CursorUp,
+    CursorDown,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    Open,
+    Parent,
+    Back,
+    Forward,
+    Copy,
+    SelectSource,
+    ToggleTarMode,
+    SwitchPrivilege,
+    Volumes,
+    Bookmarks,
+    Preview,
+    View,
+    Edit,
+    Launch,
+    Palette,
+    Help,
+}
+
+impl Action {
+    pub const ALL: &'static [Action] = &[
+        Action::Quit,
+        Action::SwitchPane,
+        Action::CursorUp,
+        Action::CursorDown,
+        Action::PageUp,
+        Action::PageDown,
+        Action::Home,
+        Action::End,
+        Action::Open,
+        Action::Parent,
+        Action::Back,
+        Action::Forward,
+        Action::Copy,
+        Action::SelectSource,
+        Action::ToggleTarMode,
+        Action::SwitchPrivilege,
+        Action::Volumes,
+        Action::Bookmarks,
+        Action::Preview,
+        Action::View,
+        Action::Edit,
+        Action::Launch,
+        Action::Palette,
+        Action::Help,
+    ];
+
+    /// Gets name used in config file
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::SwitchPane => "switch_pane",
+            Action::CursorUp => "cursor_up",
+            Action::CursorDown => "cursor_down",
+            Action::PageUp => "page_up",
+            Action::PageDown => "page_down",
+            Action::Home => "home",
+            Action::End => "end",
+            Action::Open => "open",
+            Action::Parent => "parent",
+            Action::Back => "back",
+            Action::Forward => "forward",
+            Action::Copy => "copy",
+            Action::SelectSource => "select_source",
+            Action::ToggleTarMode => "toggle_tar_mode",
+            Action::SwitchPrivilege => "switch_privilege",
+            Action::Volumes => "volumes",
+            Action::Bookmarks => "bookmarks",
+            Action::Preview => "preview",
+            Action::View => "view",
+            Action::Edit => "edit",
+            Action::Launch => "launch",
+            Action::Palette => "palette",
+            Action::Help => "help",
+        }
+    }
+
+    /// Gets short label shown in hint bar
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::Quit => "Quit",
+            Action::SwitchPane => "Pane",
+            Action::CursorUp => "Up",
+            Action::CursorDown => "Down",
+            Action::PageUp => "PgUp",
+            Action::PageDown => "PgDn",
+            Action::Home => "Home",
+            Action::End => "End",
+            Action::Open => "Open",
+            Action::Parent => "Parent",
+            Action::Back => "Back",
+            Action::Forward => "Forward",
+            Action::Copy => "Copy",
+            Action::SelectSource => "Source",
+            Action::ToggleTarMode => "Tar",
+            Action::SwitchPrivilege => "Privilege",
+            Action::Volumes => "Volumes",
+            Action::Bookmarks => "Bookmarks",
+            Action::Preview => "Preview",
+            Action::View => "View",
+            Action::Edit => "Edit",
+            Action::Launch => "Launch",
+            Action::Palette => "Commands",
+            Action::Help => "Help",
+        }
+    }
+
+    /// Gets one line description shown in command palette and help
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Quit => "Quit",
+            Action::SwitchPane => "Switch between panes",
+            Action::CursorUp => "Move cursor up",
+            Action::CursorDown => "Move cursor down",
+            Action::PageUp => "Move cursor up by page",
+            Action::PageDown => "Move cursor down by page",
+            Action::Home => "Move cursor to first entry",
+            Action::End => "Move cursor to last entry",
+            Action::Open => "Open directory or archive",
+            Action::Parent => "Go to parent directory",
+            Action::Back => "Go back in history",
+            Action::Forward => "Go forward in history",
+            Action::Copy => "Copy selected entry to other pane",
+            Action::SelectSource => "Select pane source: local, device or app sandbox",
+            Action::ToggleTarMode => "Toggle copying directories as tar stream",
+            Action::SwitchPrivilege => "Switch device privilege: shell, su, adb root",
+            Action::Volumes => "Go to storage volume",
+            Action::Bookmarks => "Bookmarks and recent directories",
+            Action::Preview => "Toggle preview of selected file in other pane",
+            Action::View => "View selected file with search and follow mode",
+            Action::Edit => "Edit selected file in $EDITOR, pushing changes back to device",
+            Action::Launch => "Open selected file with system handler or configured opener",
+            Action::Palette => "Command palette",
+            Action::Help => "Help with all keys",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|action| action.name() == name)
+    }
+
+    fn default_keys(self) -> &'static [&'static str] {
+        match self {
+            Action::Quit => &["Esc"],
+            Action::SwitchPane => &["Tab"],
+            Action::CursorUp => &["Up"],
+            Action::CursorDown => &["Down"],
+            Action::PageUp => &["PgUp"],
+            Action::PageDown => &["PgDn"],
+            Action::Home => &["Home"],
+            Action::End => &["End"],
+            Action::Open => &["Enter"],
+            Action::Parent => &["Backspace"],
+            Action::Back => &["Alt+Left"],
+            Action::Forward => &["Alt+Right"],
+            Action::Copy => &["F5"],
+            Action::SelectSource => &["F2"],
+            Action::ToggleTarMode => &["Ctrl+T"],
+            Action::SwitchPrivilege => &["Ctrl+R"],
+            Action::Volumes => &["Ctrl+V"],
+            Action::Bookmarks => &["Ctrl+B"],
+            Action::Preview => &["Ctrl+Q"],
+            Action::View => &["F3"],
+            Action::Edit => &["F4"],
+            Action::Launch => &["Ctrl+O"],
+            Action::Palette => &["Ctrl+P"],
+            Action::Help => &["F1"],
+        }
+    }
+
+    /// Whether action repeats while its key is held, like cursor movement
+    fn repeats(self) -> bool {
+        matches!(
+            self,
+            Action::CursorUp | Action::CursorDown | Action::PageUp | Action::PageDown
+        )
+    }
+}
+
+/// Key with modifiers, written like `Ctrl+T`, `Alt+Left` or `F5` in config file
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeyBinding {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    pub fn parse(key: &str) -> Result<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut parts = key.split('+').collect::<Vec<&str>>();
+        // Allows binding plus key itself, like `+` or `Ctrl++`
+        if key == "+" || key.ends_with("++") {
+            parts.pop();
+            parts.pop();
+            parts.push("+");
+        }
+        let name = parts.pop().unwrap_or_default();
+
+        for modifier in parts {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(anyhow!("unknown modifier \"{}\" in \"{}\"", modifier, key)),
+            };
+        }
+
+        let code = match name.to_lowercase().as_str() {
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "enter" | "return" => KeyCode::Enter,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" | "ins" => KeyCode::Insert,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" | "pgup" => KeyCode::PageUp,
+            "pagedown" | "pgdn" => KeyCode::PageDown,
+            "space" => KeyCode::Char(' '),
+            lower => match (lower.strip_prefix('f'), name.chars().count()) {
+                (Some(number), _) if !number.is_empty() => match number.parse::<u8>() {
+                    Ok(number @ 1..=12) => KeyCode::F(number),
+                    _ => return Err(anyhow!("unknown key \"{}\"", key)),
+                },
+                (_, 1) => {
+                    let char = name.chars().next().unwrap();
+                    // Terminals report Ctrl and Alt letters in lower case
+                    if modifiers.is_empty() {
+                        KeyCode::Char(char)
+                    } else {
+                        KeyCode::Char(char.to_ascii_lowercase())
+                    }
+                }
+                _ => return Err(anyhow!("unknown key \"{}\"", key)),
+            },
+        };
+
+        Ok(Self { code, modifiers })
+    }
+
+    fn is_pressed(&self, engine: &ConsoleEngine) -> bool {
+        engine.is_key_pressed_with_modifier(self.code, self.modifiers)
+    }
+
+    fn is_held(&self, engine: &ConsoleEngine) -> bool {
+        engine.is_key_held_with_modifier(self.code, self.modifiers)
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "Shift+")?;
+        }
+
+        match self.code {
+            KeyCode::F(number) => write!(f, "F{}", number),
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(char) if self.modifiers.is_empty() => write!(f, "{}", char),
+            KeyCode::Char(char) => write!(f, "{}", char.to_ascii_uppercase()),
+            KeyCode::PageUp => write!(f, "PgUp"),
+            KeyCode::PageDown => write!(f, "PgDn"),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+/// Keys bound to each action
+#[derive(Clone)]
+pub struct Keymap {
+    bindings: BTreeMap<Action, Vec<KeyBinding>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = Action::ALL
+            .iter()
+            .map(|&action| {
+                let keys = action
+                    .default_keys()
+                    .iter()
+                    .map(|key| KeyBinding::parse(key).unwrap())
+                    .collect();
+                (action, keys)
+            })
+            .collect();
+
+        Self { bindings }
+    }
+}
+
+impl Keymap {
+    /// Replaces keys of `action`
+    pub fn bind(&mut self, action: Action, keys: Vec<KeyBinding>) {
+        self.bindings.insert(action, keys);
+    }
+
+    pub fn get_keys(&self, action: Action) -> &[KeyBinding] {
+        self.bindings.get(&action).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Finds actions bound to the same key. Returns key and both actions
+    pub fn find_conflicts(&self) -> Vec<(KeyBinding, Action, Action)> {
+        let mut conflicts = Vec::new();
+        let bindings = self
+            .bindings
+            .iter()
+            .flat_map(|(&action, keys)| keys.iter().map(move |&key| (key, action)))
+            .collect::<Vec<(KeyBinding, Action)>>();
+
+        for (idx, (key, action)) in bindings.iter().enumerate() {
+            for (other_key, other_action) in &bindings[idx + 1..] {
+                if key == other_key && action != other_action {
+                    conflicts.push((*key, *action, *other_action));
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// Checks whether any key of `action` was pressed during current frame
+    pub fn is_pressed(&self, engine: &ConsoleEngine, action: Action) -> bool {
+        self.get_keys(action).iter().any(|key| {
+            if action.repeats() {
+                key.is_held(engine)
+            } else {
+                key.is_pressed(engine)
+            }
+        })
+    }
+
+    /// Gets actions whose keys were pressed during current frame
+    pub fn pressed_actions(&self, engine: &ConsoleEngine) -> Vec<Action> {
+        Action::ALL
+            .iter()
+            .copied()
+            .filter(|&action| self.is_pressed(engine, action))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyBinding {
+        KeyBinding { code, modifiers }
+    }
+
+    #[test]
+    fn parse_named_keys() {
+        assert_eq!(
+            KeyBinding::parse("Esc").unwrap(),
+            key(KeyCode::Esc, KeyModifiers::NONE)
+        );
+        assert_eq!(
+            KeyBinding::parse("pgdn").unwrap(),
+            key(KeyCode::PageDown, KeyModifiers::NONE)
+        );
+        assert_eq!(
+            KeyBinding::parse("Space").unwrap(),
+            key(KeyCode::Char(' '), KeyModifiers::NONE)
+        );
+    }
+
+    #[test]
+    fn parse_function_keys() {
+        assert_eq!(
+            KeyBinding::parse("F1").unwrap(),
+            key(KeyCode::F(1), KeyModifiers::NONE)
+        );
+        assert_eq!(
+            KeyBinding::parse("f12").unwrap(),
+            key(KeyCode::F(12), KeyModifiers::NONE)
+        );
+        assert!(KeyBinding::parse("F13").is_err());
+        assert!(KeyBinding::parse("F0").is_err());
+    }
+
+    #[test]
+    fn parse_single_chars() {
+        assert_eq!(
+            KeyBinding::parse("q").unwrap(),
+            key(KeyCode::Char('q'), KeyModifiers::NONE)
+        );
+        assert_eq!(
+            KeyBinding::parse("Q").unwrap(),
+            key(KeyCode::Char('Q'), KeyModifiers::NONE)
+        );
+        assert_eq!(
+            KeyBinding::parse("F").unwrap(),
+            key(KeyCode::Char('F'), KeyModifiers::NONE)
+        );
+        assert_eq!(
+            KeyBinding::parse("ä").unwrap(),
+            key(KeyCode::Char('ä'), KeyModifiers::NONE)
+        );
+    }
+
+    #[test]
+    fn parse_modifiers() {
+        assert_eq!(
+            KeyBinding::parse("Ctrl+T").unwrap(),
+            key(KeyCode::Char('t'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(
+            KeyBinding::parse("alt+shift+Left").unwrap(),
+            key(KeyCode::Left, KeyModifiers::ALT | KeyModifiers::SHIFT)
+        );
+        assert_eq!(
+            KeyBinding::parse("Ctrl++").unwrap(),
+            key(KeyCode::Char('+'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(
+            KeyBinding::parse("+").unwrap(),
+            key(KeyCode::Char('+'), KeyModifiers::NONE)
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(KeyBinding::parse("").is_err());
+        assert!(KeyBinding::parse("Hyper+T").is_err());
+        assert!(KeyBinding::parse("Ctrl+").is_err());
+        assert!(KeyBinding::parse("Ctrl+Foo").is_err());
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for name in ["Ctrl+T", "Alt+Left", "F5", "PgUp", "Space", "q"] {
+            assert_eq!(KeyBinding::parse(name).unwrap().to_string(), name);
+        }
+    }
+}
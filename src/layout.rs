@@ -1,5 +1,5 @@
-/// Slightly modified version of 'shoji' layout engine
-/// Originally created by Richard Anaya under MIT license 
+//! Slightly modified version of 'shoji' layout engine
+//! Originally created by Richard Anaya under MIT license
 
 extern crate alloc;
 use alloc::vec::Vec;
@@ -21,7 +21,6 @@ impl Default for LayoutStyle {
 }
 
 pub enum Direction {
-    TopBottom,
     LeftRight,
 }
 
@@ -138,28 +137,6 @@ impl LayoutEngine {
                         None => Err("cannot compute layout of LeftRight without defined width"),
                     }
                 }
-                Direction::TopBottom => {
-                    let width = s.width;
-                    let height = s.height;
-                    match height {
-                        Some(h) => {
-                            let child_height = h / children.len() as i32;
-                            for (i, c) in children.iter().enumerate() {
-                                self.compute_layout_helper(
-                                    x,
-                                    y + i as i32 * child_height,
-                                    *c,
-                                    LayoutSize {
-                                        width,
-                                        height: Some(child_height),
-                                    },
-                                )?;
-                            }
-                            Ok(())
-                        }
-                        None => Err("cannot compute layout of TopBottom without defined width"),
-                    }
-                }
             }
         }
     }
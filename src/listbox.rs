@@ -1,131 +1,255 @@
-use console_engine::{pixel, screen::Screen, Color, ConsoleEngine, KeyCode};
+use std::time::{Duration, Instant};
 
-use crate::layout::Layout;
+use console_engine::{pixel, screen::Screen, Color, ConsoleEngine};
 
+use crate::{config, keymap::Action, layout::Layout};
+
+/// Item that can be shown in listbox
 pub trait ListBoxItem {
     fn get_entry(&self) -> String;
     fn bg_color(&self) -> Color;
     fn fg_color(&self) -> Color;
+    /// Type glyph drawn before entry. Items without glyph are drawn as is
+    fn glyph(&self) -> Option<char> {
+        None
+    }
+}
+
+impl ListBoxItem for String {
+    fn get_entry(&self) -> String {
+        self.clone()
+    }
+
+    fn bg_color(&self) -> Color {
+        Color::Reset
+    }
+
+    fn fg_color(&self) -> Color {
+        config::get().theme.list_fg
+    }
+}
+
+/// Second click on the same item within this time is a double click
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+/// Items scrolled by one mouse wheel step
+const WHEEL_STEP: usize = 3;
+
+/// Result of mouse click on listbox item
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Click {
+    /// Cursor moved to clicked item
+    Select,
+    /// Item was double clicked
+    Activate,
+}
+
+/// Computes scrollbar thumb of `height` rows tall track: first row and size.
+/// Returns `None` if all `len` items fit and there is nothing to scroll
+fn scrollbar_thumb(len: usize, height: usize, scroll: usize) -> Option<(usize, usize)> {
+    if len <= height || height == 0 {
+        return None;
+    }
+    let size = (height * height / len).max(1);
+    let start = scroll * (height - size) / (len - height);
+    Some((start, size))
 }
 
 #[derive(Clone)]
 /// A listbox user interface
-pub struct ListBox {
+pub struct ListBox<T: ListBoxItem = String> {
     /// Listbox items
-    pub display: Vec<String>,
-    /// Current displaying page
-    pub page: usize,
-    /// Current on-page cursor position. you can safely read/write this value
-    pub position: usize,
+    pub display: Vec<T>,
+    /// Index of item under cursor
+    selected: usize,
+    /// Index of first visible item. Follows cursor so it always stays visible
+    scroll: usize,
     screen: Screen,
     /// In focus this listbox?
     pub focused: bool,
     x: i32,
     y: i32,
+    /// Time and item index of last mouse click, to detect double clicks
+    last_click: Option<(Instant, usize)>,
 }
 
-impl ListBox {
+impl<T: ListBoxItem> ListBox<T> {
     /// Creates listbox
     pub fn new(layout: &Layout, focused: bool) -> Self {
         Self {
             display: Vec::new(),
             x: layout.x,
             y: layout.y,
-            page: 0,
-            position: 0,
+            selected: 0,
+            scroll: 0,
             screen: Screen::new_fill(layout.w as u32, layout.h as u32, pixel::pxl(' ')),
             focused,
+            last_click: None,
         }
     }
 
-    /// Gets listbox page count
-    pub fn get_page_count(&mut self) -> usize {
-        self.display
-            .chunks((self.screen.get_height() - 2) as usize)
-            .len()
+    /// Gets number of visible items
+    fn page_height(&self) -> usize {
+        (self.screen.get_height() as usize).max(1)
     }
 
-    /// Scrolls listbox down by 1 item. if reaches end of current page - switches to another
-    pub fn scroll_down(&mut self) {
-        if self.position < self.screen.get_height() as usize - 2 {
-            self.position += 1;
-        } else {
-            self.switch_page_up();
+    /// Scrolls viewport so cursor is visible
+    fn follow_cursor(&mut self) {
+        let page_h = self.page_height();
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + page_h {
+            self.scroll = self.selected + 1 - page_h;
         }
+        self.scroll = self.scroll.min(self.display.len().saturating_sub(page_h));
+    }
+
+    /// Gets index of first visible item
+    pub fn get_scroll(&self) -> usize {
+        self.scroll
+    }
+
+    /// Scrolls to `scroll` and moves cursor to item `selected`, adjusting scroll if cursor would
+    /// not be visible
+    pub fn set_view(&mut self, selected: usize, scroll: usize) {
+        self.scroll = scroll;
+        self.set_cursor(selected);
     }
 
-    /// Scrolls listbox up by 1 item. if reaches start of current page - switches to another
+    /// Moves cursor to item `idx`, clamped to current content
+    pub fn set_cursor(&mut self, idx: usize) {
+        self.selected = idx.min(self.display.len().saturating_sub(1));
+        self.follow_cursor();
+    }
+
+    /// Moves cursor down by 1 item
+    pub fn scroll_down(&mut self) {
+        self.set_cursor(self.selected + 1);
+    }
+
+    /// Moves cursor up by 1 item
     pub fn scroll_up(&mut self) {
-        if self.position > 0 {
-            self.position -= 1;
-        } else {
-            self.switch_page_down();
-            self.position = self.screen.get_height() as usize - 2;
-        }
+        self.set_cursor(self.selected.saturating_sub(1));
     }
 
-    /// Scrolls listbox up by 1 page
-    pub fn switch_page_up(&mut self) {
-        if self.page < self.get_page_count() - 1 {
-            self.page += 1;
-            self.position = 0;
-        }
-        self.screen.clear();
+    /// Moves cursor down by visible height
+    pub fn page_down(&mut self) {
+        self.set_cursor(self.selected + self.page_height());
     }
 
-    /// Scrolls listbox down by 1 page
-    pub fn switch_page_down(&mut self) {
-        if self.page > 0 {
-            self.page -= 1;
-        }
-        self.screen.clear();
+    /// Moves cursor up by visible height
+    pub fn page_up(&mut self) {
+        self.set_cursor(self.selected.saturating_sub(self.page_height()));
+    }
+
+    /// Moves cursor to first item
+    pub fn home(&mut self) {
+        self.set_cursor(0);
     }
 
+    /// Moves cursor to last item
+    pub fn end(&mut self) {
+        self.set_cursor(self.display.len().saturating_sub(1));
+    }
 
-    pub fn set_content(&mut self, content: Vec<String>) {
-        self.screen.clear();
+    pub fn set_content(&mut self, content: Vec<T>) {
         self.display = content;
-        self.page = 0;
-        self.position = 0;
+        self.selected = 0;
+        self.scroll = 0;
     }
 
-    /// Gets selected listbox index
-    pub fn get_selected_idx(&mut self) -> usize {
-        let pos = self.position + (self.page * self.screen.get_height() as usize);
-        pos.checked_sub(2).unwrap_or(0)
+    /// Moves cursor to item equal to `value`. Returns `false` if there is no such item
+    pub fn select(&mut self, value: &str) -> bool {
+        match self
+            .display
+            .iter()
+            .position(|item| item.get_entry() == value)
+        {
+            Some(idx) => {
+                self.set_cursor(idx);
+                true
+            }
+            None => false,
+        }
     }
 
-    /// Removes listbox items by value
-    pub fn remove(&mut self, value: String) {
-        self.display.retain(|x| x == &value);
+    /// Gets selected listbox index
+    pub fn get_selected_idx(&self) -> usize {
+        self.selected
     }
 
-    /// Gets current selected String in listbox
-    pub fn get_selected_str(&mut self) -> String {
-        self.display[self.position].clone()
+    /// Gets item under cursor
+    pub fn get_selected(&self) -> Option<&T> {
+        self.display.get(self.selected)
     }
 
-    /// Sets listbox position and sets the needed page
-    pub fn sel_idx_glob(&mut self, pos: usize) -> usize {
-        pos + (self.page * self.screen.get_height() as usize)
+    /// Gets current selected String in listbox, or empty string if listbox is empty
+    pub fn get_selected_str(&self) -> String {
+        self.get_selected()
+            .map(ListBoxItem::get_entry)
+            .unwrap_or_default()
     }
 
-    /// Resizes listbox to specified dimensions
+    /// Resizes listbox to specified dimensions, keeping cursor visible
     pub fn resize(&mut self, layout: &Layout) {
-        self.position = 0;
-        self.screen.clear();
         self.screen.resize(layout.w as u32, layout.h as u32);
         self.x = layout.x;
         self.y = layout.y;
+        self.follow_cursor();
+    }
+
+    /// Moves cursor for cursor actions, ignores other actions
+    pub fn handle_action(&mut self, action: Action) {
+        match action {
+            Action::CursorDown => self.scroll_down(),
+            Action::CursorUp => self.scroll_up(),
+            Action::PageDown => self.page_down(),
+            Action::PageUp => self.page_up(),
+            Action::Home => self.home(),
+            Action::End => self.end(),
+            _ => {}
+        }
+    }
+
+    /// Checks whether point `x`, `y` is inside listbox. Coordinates are in the same space as
+    /// listbox position
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        (self.x..self.x + self.screen.get_width() as i32).contains(&x)
+            && (self.y..self.y + self.screen.get_height() as i32).contains(&y)
+    }
+
+    /// Gets index of item drawn at `x`, `y`
+    pub fn item_at(&self, x: i32, y: i32) -> Option<usize> {
+        if !self.contains(x, y) {
+            return None;
+        }
+        let idx = self.scroll + (y - self.y) as usize;
+        (idx < self.display.len()).then_some(idx)
     }
 
-    pub fn handle_events(&mut self, engine: &ConsoleEngine) {
-        if self.focused {
-            if engine.is_key_held(KeyCode::Down) {
+    /// Moves cursor to item clicked at `x`, `y`. Returns `None` if there is no item there
+    pub fn click(&mut self, x: i32, y: i32) -> Option<Click> {
+        let idx = self.item_at(x, y)?;
+        self.set_cursor(idx);
+
+        let double = matches!(self.last_click, Some((time, last_idx))
+            if last_idx == idx && time.elapsed() < DOUBLE_CLICK_INTERVAL);
+        if double {
+            self.last_click = None;
+            Some(Click::Activate)
+        } else {
+            self.last_click = Some((Instant::now(), idx));
+            Some(Click::Select)
+        }
+    }
+
+    /// Moves cursor with mouse wheel
+    pub fn handle_wheel(&mut self, engine: &ConsoleEngine) {
+        for _ in 0..WHEEL_STEP {
+            if engine.is_mouse_scrolled_down() {
                 self.scroll_down();
             }
-
-            if engine.is_key_held(KeyCode::Up) {
+            if engine.is_mouse_scrolled_up() {
                 self.scroll_up();
             }
         }
@@ -135,35 +259,215 @@ impl ListBox {
         (self.x, self.y)
     }
 
+    /// Gets position and size of listbox
+    pub fn get_layout(&self) -> Layout {
+        Layout {
+            x: self.x,
+            y: self.y,
+            w: self.screen.get_width() as i32,
+            h: self.screen.get_height() as i32,
+        }
+    }
+
     /// Draws listbox. For more stability recommended to use in print_screen function
     pub fn draw(&mut self) -> &Screen {
-        let splited_pags = self.display.chunks(self.screen.get_height() as usize);
+        let theme = &config::get().theme;
+        let fg_color = if self.focused {
+            theme.list_fg
+        } else {
+            theme.list_inactive_fg
+        };
+        let text_w = (self.screen.get_width() as usize).saturating_sub(1);
+        let page_h = self.page_height();
 
-        let mut fg_color = Color::Reset;
+        self.screen.clear();
+        for (row, (idx, item)) in self
+            .display
+            .iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(page_h)
+            .enumerate()
+        {
+            let entry = match item.glyph() {
+                Some(glyph) => format!("{} {}", glyph, item.get_entry()),
+                None => item.get_entry(),
+            };
+            let text = entry.chars().take(text_w).collect::<String>();
+            let (fg, bg) = if idx == self.selected && self.focused {
+                (theme.list_cursor_fg, theme.list_cursor_bg)
+            } else if self.focused {
+                (item.fg_color(), item.bg_color())
+            } else {
+                (fg_color, Color::Reset)
+            };
+            self.screen.print_fbg(0, row as i32, &text, fg, bg);
+        }
 
-        if !self.focused {
-            fg_color = Color::DarkGrey;
+        let thumb = scrollbar_thumb(self.display.len(), page_h, self.scroll);
+        for y in 0..page_h {
+            let in_thumb = thumb.is_some_and(|(start, size)| (start..start + size).contains(&y));
+            let (char, color) = if in_thumb {
+                ("█", fg_color)
+            } else {
+                ("│", theme.list_inactive_fg)
+            };
+            self.screen
+                .print_fbg(text_w as i32, y as i32, char, color, Color::Reset);
         }
 
-        for (i, v) in &mut splited_pags.into_iter().enumerate() {
-            if i == self.page {
-                for (index, page) in v.into_iter().enumerate() {
-                    if index == self.position && self.focused {
-                        self.screen
-                            .print_fbg(0, index as i32, page, fg_color, Color::White)
-                    } else {
-                        self.screen
-                            .print_fbg(0, index as i32, page, fg_color, Color::Reset);
-                    }
-                }
-            }
+        &self.screen
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listbox(items: usize, height: i32) -> ListBox {
+        let mut listbox = ListBox::new(
+            &Layout {
+                x: 0,
+                y: 0,
+                w: 20,
+                h: height,
+            },
+            true,
+        );
+        listbox.set_content((0..items).map(|idx| idx.to_string()).collect());
+        listbox
+    }
+
+    #[test]
+    fn cursor_stops_at_ends() {
+        let mut listbox = listbox(3, 10);
+        listbox.scroll_up();
+        assert_eq!(listbox.get_selected_idx(), 0);
+
+        for _ in 0..5 {
+            listbox.scroll_down();
         }
+        assert_eq!(listbox.get_selected_idx(), 2);
+    }
 
-        for y in 0..self.screen.get_height() {
-            self.screen
-                .print(self.screen.get_width() as i32 - 1, y as i32, "|");
+    #[test]
+    fn viewport_follows_cursor() {
+        let mut listbox = listbox(20, 5);
+        for _ in 0..7 {
+            listbox.scroll_down();
         }
+        assert_eq!(listbox.get_selected_idx(), 7);
+        assert_eq!(listbox.scroll, 3);
 
-        &self.screen
+        for _ in 0..5 {
+            listbox.scroll_up();
+        }
+        assert_eq!(listbox.get_selected_idx(), 2);
+        assert_eq!(listbox.scroll, 2);
+    }
+
+    #[test]
+    fn selected_str_below_first_page() {
+        let mut listbox = listbox(20, 5);
+        for _ in 0..12 {
+            listbox.scroll_down();
+        }
+        assert_eq!(listbox.get_selected_str(), "12");
+    }
+
+    #[test]
+    fn page_keys() {
+        let mut listbox = listbox(12, 5);
+        listbox.page_down();
+        assert_eq!(listbox.get_selected_idx(), 5);
+        listbox.page_down();
+        listbox.page_down();
+        assert_eq!(listbox.get_selected_idx(), 11);
+        assert_eq!(listbox.scroll, 7);
+
+        listbox.page_up();
+        assert_eq!(listbox.get_selected_idx(), 6);
+        listbox.page_up();
+        listbox.page_up();
+        assert_eq!(listbox.get_selected_idx(), 0);
+        assert_eq!(listbox.scroll, 0);
+    }
+
+    #[test]
+    fn home_and_end() {
+        let mut listbox = listbox(30, 5);
+        listbox.end();
+        assert_eq!(listbox.get_selected_str(), "29");
+        assert_eq!(listbox.scroll, 25);
+
+        listbox.home();
+        assert_eq!(listbox.get_selected_idx(), 0);
+        assert_eq!(listbox.scroll, 0);
+    }
+
+    #[test]
+    fn empty_listbox() {
+        let mut listbox = listbox(0, 5);
+        listbox.scroll_down();
+        listbox.page_down();
+        listbox.end();
+        assert_eq!(listbox.get_selected_idx(), 0);
+        assert_eq!(listbox.get_selected_str(), "");
+    }
+
+    #[test]
+    fn select_and_set_content() {
+        let mut listbox = listbox(20, 5);
+        assert!(listbox.select("15"));
+        assert_eq!(listbox.get_selected_idx(), 15);
+        assert!(!listbox.select("missing"));
+        assert_eq!(listbox.get_selected_idx(), 15);
+
+        listbox.set_content(vec!["a".to_string()]);
+        assert_eq!(listbox.get_selected_idx(), 0);
+        assert_eq!(listbox.scroll, 0);
+    }
+
+    #[test]
+    fn resize_keeps_cursor_visible() {
+        let mut listbox = listbox(20, 10);
+        listbox.set_cursor(9);
+        listbox.resize(&Layout {
+            x: 0,
+            y: 0,
+            w: 20,
+            h: 4,
+        });
+        assert_eq!(listbox.get_selected_idx(), 9);
+        assert_eq!(listbox.scroll, 6);
+    }
+
+    #[test]
+    fn set_view_restores_scroll() {
+        let mut listbox = listbox(20, 5);
+        listbox.set_view(8, 6);
+        assert_eq!((listbox.get_selected_idx(), listbox.get_scroll()), (8, 6));
+        listbox.set_view(15, 6);
+        assert_eq!((listbox.get_selected_idx(), listbox.get_scroll()), (15, 11));
+        listbox.set_view(2, 18);
+        assert_eq!((listbox.get_selected_idx(), listbox.get_scroll()), (2, 2));
+    }
+
+    #[test]
+    fn click_selects_scrolled_item() {
+        let mut listbox = listbox(20, 5);
+        listbox.set_cursor(10);
+        assert_eq!(listbox.click(3, 1), Some(Click::Select));
+        assert_eq!(listbox.get_selected_str(), "7");
+        assert_eq!(listbox.click(3, 1), Some(Click::Activate));
+        assert_eq!(listbox.click(3, 7), None);
+    }
+
+    #[test]
+    fn scrollbar() {
+        assert_eq!(scrollbar_thumb(5, 10, 0), None);
+        assert_eq!(scrollbar_thumb(20, 10, 0), Some((0, 5)));
+        assert_eq!(scrollbar_thumb(20, 10, 10), Some((5, 5)));
+        assert_eq!(scrollbar_thumb(1000, 10, 990), Some((9, 1)));
     }
 }
@@ -1,24 +1,49 @@
 use anyhow::anyhow;
 use anyhow::Result;
-use console_engine::ConsoleEngine;
-use console_engine::KeyCode;
+use console_engine::{ConsoleEngine, MouseButton};
 use device_filelist::DeviceFilelist;
-use file_operations::{FileOperations, Local};
+use file_operations::{format_size, FileOperations, Local};
 use flexi_logger::FileSpec;
 use generational_arena::Index;
+use help::help;
+use hint_bar::{Hint, HintBar};
 use layout::{Direction, LayoutEngine, LayoutSize, LayoutStyle};
+use menu::menu;
 use modal::modal;
+use palette::palette;
+use preview::Preview;
 use std::vec;
 
-use crate::adb::Adb;
+use crate::{
+    adb::{Adb, Device},
+    bookmarks::Bookmarks,
+    config::Config,
+    keymap::Action,
+    listbox::Click,
+};
 
 mod adb;
+mod archive;
+mod bookmarks;
 mod bottom_bar;
+mod config;
+mod conflict;
 mod device_filelist;
+mod external;
 mod file_operations;
+mod help;
+mod hint_bar;
+mod keymap;
 mod layout;
 mod listbox;
+mod menu;
 mod modal;
+mod palette;
+mod preview;
+mod text_input;
+mod thumbnail;
+mod transfer;
+mod viewer;
 
 fn resize_layout(main_layout: &mut LayoutEngine, w: u16, h: u16) -> (Index, Index) {
     let left_idx = main_layout.new_node(LayoutStyle::default(), vec![]);
@@ -32,22 +57,43 @@ fn resize_layout(main_layout: &mut LayoutEngine, w: u16, h: u16) -> (Index, Inde
     );
 
     main_layout
-        .compute_layout(root, LayoutSize::new(w as i32, h as i32 - 1))
+        .compute_layout(root, LayoutSize::new(w as i32, h as i32 - 2))
         .unwrap();
 
-    return (left_idx, right_idx);
+    (left_idx, right_idx)
 }
 
 fn main() {
-    flexi_logger::Logger::try_with_str("warn, adbexplorer=debug")
+    let (config, config_errors) = Config::load();
+    flexi_logger::Logger::try_with_str(format!("warn, adbexplorer={}", config.log_level))
         .unwrap()
         .log_to_file(FileSpec::default())
         .format_for_files(flexi_logger::with_thread)
         .start()
         .unwrap();
+    config::init(config);
 
     let mut engine = console_engine::ConsoleEngine::init_fill(30)
         .expect("Unable to create console engine instance");
+
+    if !config_errors.is_empty() {
+        for error in &config_errors {
+            log::error!("Config: {}", error);
+        }
+
+        let result = modal(
+            &mut engine,
+            format!(
+                "Errors in {}, defaults are used instead:\n{}",
+                config::config_path().display(),
+                config_errors.join("\n")
+            ),
+            vec!["Continue", "Exit"],
+        );
+        if result.as_str() == "Exit" {
+            return;
+        }
+    }
     loop {
         if let Err(err) = main_inner(&mut engine) {
             let result = modal(
@@ -63,16 +109,472 @@ fn main() {
     }
 }
 
+fn check_authorized(device: &Device) -> Result<()> {
+    if !device.is_authorized() {
+        return Err(anyhow!(
+            "Device {} is unauthorized. Allow USB debugging on phone",
+            device.get_name()
+        ));
+    }
+    Ok(())
+}
+
+/// Source that can be opened in a pane
+enum Source {
+    Local,
+    Device(usize),
+    /// Sandbox of debuggable app on device, through `run-as`
+    AppSandbox(usize),
+}
+
+/// Asks user to pick new source for a pane: local filesystem, one of connected devices or app
+/// sandbox on a device
+fn select_source(
+    engine: &mut ConsoleEngine,
+    adb: &mut Adb,
+) -> Result<Option<Box<dyn FileOperations>>> {
+    adb.populate_devices()?;
+
+    let mut sources = vec![Source::Local];
+    let mut items = vec!["Local filesystem".to_string()];
+    for (idx, device) in adb.devices.iter().enumerate() {
+        sources.push(Source::Device(idx));
+        items.push(format!("Device {}", device.get_name()));
+        sources.push(Source::AppSandbox(idx));
+        items.push(format!("App sandbox on {}", device.get_name()));
+    }
+
+    let idx = match menu(engine, "Pane source", items) {
+        Some(idx) => idx,
+        None => return Ok(None),
+    };
+
+    match sources[idx] {
+        Source::Local => Ok(Some(Box::new(Local::new()?))),
+        Source::Device(device_idx) | Source::AppSandbox(device_idx) => {
+            let device = adb.devices[device_idx].clone();
+            if let Err(err) = check_authorized(&device) {
+                modal(engine, err.to_string(), vec!["OK"]);
+                return Ok(None);
+            }
+
+            if let Source::AppSandbox(_) = sources[idx] {
+                return select_app_sandbox(engine, device);
+            }
+            Ok(Some(Box::new(device)))
+        }
+    }
+}
+
+/// Asks user to pick app package and opens its sandbox on `device`
+fn select_app_sandbox(
+    engine: &mut ConsoleEngine,
+    device: Device,
+) -> Result<Option<Box<dyn FileOperations>>> {
+    let packages = device.list_packages()?;
+    if packages.is_empty() {
+        modal(
+            engine,
+            format!("Device {} has no debuggable apps", device.get_name()),
+            vec!["OK"],
+        );
+        return Ok(None);
+    }
+    let package = match menu(engine, "App package", packages.clone()) {
+        Some(idx) => &packages[idx],
+        None => return Ok(None),
+    };
+
+    match device.run_as(package) {
+        Ok(device) => Ok(Some(Box::new(device))),
+        Err(err) => {
+            modal(engine, err.to_string(), vec!["OK"]);
+            Ok(None)
+        }
+    }
+}
+
+/// Asks user to pick one of storage volumes of `files`. Returns volume path
+fn select_volume(
+    engine: &mut ConsoleEngine,
+    files: &mut dyn FileOperations,
+) -> Result<Option<String>> {
+    let volumes = files.volumes()?;
+    if volumes.is_empty() {
+        modal(
+            engine,
+            format!("{} has no storage volumes", files.get_source_name()),
+            vec!["OK"],
+        );
+        return Ok(None);
+    }
+
+    let items = volumes
+        .iter()
+        .map(|volume| {
+            format!(
+                "{}  {} free of {}",
+                volume.name,
+                format_size(volume.free),
+                format_size(volume.total)
+            )
+        })
+        .collect();
+
+    Ok(menu(engine, "Storage volumes", items).map(|idx| volumes[idx].path.clone()))
+}
+
+/// Shows bookmarks and recent directories of `files` source and lets user add or remove current
+/// directory. Returns directory to go to
+fn select_bookmark(
+    engine: &mut ConsoleEngine,
+    bookmarks: &mut Bookmarks,
+    files: &dyn FileOperations,
+) -> Result<Option<String>> {
+    let key = match files.get_source_key() {
+        Some(key) => key,
+        None => {
+            modal(
+                engine,
+                format!("{} can not have bookmarks", files.get_source_name()),
+                vec!["OK"],
+            );
+            return Ok(None);
+        }
+    };
+    let current = files.get_working_directory().to_string();
+
+    let mut paths = vec![current.clone()];
+    let mut items = vec![if bookmarks.is_bookmarked(&key, &current) {
+        format!("- Remove bookmark {}", current)
+    } else {
+        format!("+ Bookmark {}", current)
+    }];
+    for bookmark in bookmarks.get_bookmarks(&key) {
+        paths.push(bookmark.clone());
+        items.push(format!("* {}", bookmark));
+    }
+    for recent in bookmarks.get_recent(&key).iter().skip(1) {
+        paths.push(recent.clone());
+        items.push(format!("  {}", recent));
+    }
+
+    match menu(engine, "Bookmarks and recent directories", items) {
+        Some(0) => {
+            bookmarks.toggle(&key, &current);
+            Ok(None)
+        }
+        Some(idx) => Ok(Some(paths[idx].clone())),
+        None => Ok(None),
+    }
+}
+
+/// Application state that actions work on
+struct App {
+    adb: Adb,
+    bookmarks: Bookmarks,
+    left_pane: DeviceFilelist,
+    right_pane: DeviceFilelist,
+    /// Directories are copied as single tar stream
+    tar_mode: bool,
+    /// Entry dragged with mouse, copied when dropped on the other pane
+    dragging: Option<String>,
+    /// Quick view of selected file, drawn in place of inactive pane while set
+    preview: Option<Preview>,
+}
+
+impl App {
+    /// Gets focused pane and the other one
+    fn panes(&mut self) -> (&mut DeviceFilelist, &mut DeviceFilelist) {
+        if self.left_pane.listbox.focused {
+            (&mut self.left_pane, &mut self.right_pane)
+        } else {
+            (&mut self.right_pane, &mut self.left_pane)
+        }
+    }
+
+    fn focused_pane(&mut self) -> &mut DeviceFilelist {
+        self.panes().0
+    }
+
+    /// Draws panes, replacing inactive one with preview of focused pane entry when it is on
+    fn draw(&mut self, engine: &mut ConsoleEngine) {
+        let (focused, other) = if self.left_pane.listbox.focused {
+            (&mut self.left_pane, &mut self.right_pane)
+        } else {
+            (&mut self.right_pane, &mut self.left_pane)
+        };
+
+        let (x, y) = focused.listbox.get_position();
+        engine.print_screen(x, y, focused.listbox.draw());
+        match &mut self.preview {
+            Some(preview) => {
+                let selected = focused.listbox.get_selected_str();
+                preview.update(engine, focused.device_files.as_mut(), &selected);
+                preview.set_layout(&other.listbox.get_layout());
+                let (x, y) = preview.get_position();
+                engine.print_screen(x, y, preview.draw());
+            }
+            None => {
+                let (x, y) = other.listbox.get_position();
+                engine.print_screen(x, y, other.listbox.draw());
+            }
+        }
+    }
+
+    /// Handles mouse: clicks focus pane and select entries, double clicks open them,
+    /// wheel scrolls focused pane and dragging entry to the other pane copies it
+    fn handle_mouse(&mut self, engine: &mut ConsoleEngine) {
+        if let Some((x, y)) = engine.get_mouse_press(MouseButton::Left) {
+            let (x, y) = (x as i32, y as i32);
+            let left = self.left_pane.listbox.contains(x, y);
+            let right = self.right_pane.listbox.contains(x, y);
+            // Inactive pane is covered by preview
+            let covered = self.preview.is_some() && left != self.left_pane.listbox.focused;
+            if (left || right) && !covered {
+                self.left_pane.listbox.focused = left;
+                self.right_pane.listbox.focused = !left;
+
+                let pane = self.focused_pane();
+                match pane.listbox.click(x, y) {
+                    Some(Click::Select) => {
+                        self.dragging = Some(self.focused_pane().listbox.get_selected_str())
+                    }
+                    Some(Click::Activate) => self.run(engine, Action::Open),
+                    None => {}
+                }
+            }
+        }
+
+        if let Some((x, y)) = engine.get_mouse_released(MouseButton::Left) {
+            if self.dragging.take().is_some() && self.panes().1.listbox.contains(x as i32, y as i32)
+            {
+                self.run(engine, Action::Copy);
+            }
+        }
+
+        self.focused_pane().listbox.handle_wheel(engine);
+    }
+
+    /// Gets actions available in current context with their hint bar labels
+    fn hints(&self) -> Vec<(Action, Hint)> {
+        let focused = if self.left_pane.listbox.focused {
+            &self.left_pane
+        } else {
+            &self.right_pane
+        };
+        let mut actions = vec![
+            (Action::Help, Action::Help.label()),
+            (Action::SwitchPane, Action::SwitchPane.label()),
+        ];
+
+        if focused.can_go_up() {
+            let label = if focused.at_archive_root() {
+                "Leave"
+            } else {
+                Action::Parent.label()
+            };
+            actions.push((Action::Parent, label));
+        }
+        if focused.can_go_back() {
+            actions.push((Action::Back, Action::Back.label()));
+        }
+        if focused.can_go_forward() {
+            actions.push((Action::Forward, Action::Forward.label()));
+        }
+
+        let copy = match (self.left_pane.listbox.focused, self.tar_mode) {
+            (true, false) => "Copy>",
+            (true, true) => "Tar>",
+            (false, false) => "<Copy",
+            (false, true) => "<Tar",
+        };
+        actions.push((Action::Copy, copy));
+        actions.push((Action::SelectSource, Action::SelectSource.label()));
+        if focused.device_files.has_privilege_modes() {
+            actions.push((Action::SwitchPrivilege, Action::SwitchPrivilege.label()));
+        }
+        actions.extend(
+            [
+                Action::Volumes,
+                Action::Bookmarks,
+                Action::View,
+                Action::Edit,
+                Action::Launch,
+                Action::Preview,
+                Action::ToggleTarMode,
+                Action::Palette,
+                Action::Quit,
+            ]
+            .map(|action| (action, action.label())),
+        );
+
+        actions
+            .into_iter()
+            .filter_map(|(action, label)| Some((action, Hint::for_action(action, label)?)))
+            .collect()
+    }
+
+    /// Runs `action`, whether it was triggered by its keys or chosen in command palette.
+    /// Failed action is reported in error window and leaves panes as they were
+    fn run(&mut self, engine: &mut ConsoleEngine, action: Action) {
+        if let Err(err) = self.run_action(engine, action) {
+            modal::error(engine, &err);
+        }
+    }
+
+    fn run_action(&mut self, engine: &mut ConsoleEngine, action: Action) -> Result<()> {
+        match action {
+            Action::Quit => {
+                engine.clear_screen();
+                std::process::exit(0);
+            }
+            Action::SwitchPane => {
+                self.left_pane.listbox.focused = !self.left_pane.listbox.focused;
+                self.right_pane.listbox.focused = !self.right_pane.listbox.focused;
+            }
+            Action::CursorUp
+            | Action::CursorDown
+            | Action::PageUp
+            | Action::PageDown
+            | Action::Home
+            | Action::End
+            | Action::Open
+            | Action::Parent
+            | Action::Back
+            | Action::Forward => self.focused_pane().handle_action(action)?,
+            Action::Copy => {
+                let tar_mode = self.tar_mode;
+                let (from, to) = self.panes();
+                let selected = from.listbox.get_selected_str();
+
+                if to.device_files.is_read_only() {
+                    modal(
+                        engine,
+                        format!("{} is read-only", to.device_files.get_source_name()),
+                        vec!["OK"],
+                    );
+                    return Ok(());
+                }
+                if tar_mode && from.device_files.is_directory(selected.clone()) {
+                    transfer::copy_tar(
+                        engine,
+                        from.device_files.as_mut(),
+                        to.device_files.as_mut(),
+                        &selected,
+                    )?;
+                } else {
+                    transfer::copy(
+                        engine,
+                        from.device_files.as_mut(),
+                        to.device_files.as_mut(),
+                        &selected,
+                    )?;
+                }
+                to.update_filelist()?;
+            }
+            Action::SelectSource => {
+                if let Some(source) = select_source(engine, &mut self.adb)? {
+                    self.focused_pane().set_source(source)?;
+                }
+            }
+            Action::ToggleTarMode => self.tar_mode = !self.tar_mode,
+            Action::SwitchPrivilege => {
+                let focused_pane = self.focused_pane();
+                if let Err(err) = focused_pane.device_files.switch_privilege() {
+                    modal(engine, err.to_string(), vec!["OK"]);
+                }
+                focused_pane.update_filelist()?;
+            }
+            Action::Volumes => {
+                let focused_pane = self.focused_pane();
+                if let Some(path) = select_volume(engine, focused_pane.device_files.as_mut())? {
+                    focused_pane.go_to(&path)?;
+                }
+            }
+            Action::Bookmarks => {
+                // Borrows pane field directly, since bookmarks are borrowed too
+                let focused_pane = if self.left_pane.listbox.focused {
+                    &mut self.left_pane
+                } else {
+                    &mut self.right_pane
+                };
+                if let Some(path) = select_bookmark(
+                    engine,
+                    &mut self.bookmarks,
+                    focused_pane.device_files.as_ref(),
+                )? {
+                    focused_pane.go_to(&path)?;
+                }
+            }
+            Action::Preview => {
+                self.preview = match self.preview {
+                    Some(_) => None,
+                    None => Some(Preview::new(&self.panes().1.listbox.get_layout())),
+                };
+            }
+            Action::View => {
+                let focused_pane = self.focused_pane();
+                let selected = focused_pane.listbox.get_selected_str();
+                if !selected.is_empty() && !selected.ends_with('/') {
+                    if let Err(err) =
+                        viewer::view(engine, focused_pane.device_files.as_mut(), &selected)
+                    {
+                        modal(engine, format!("Can not view file: {}", err), vec!["OK"]);
+                    }
+                }
+            }
+            Action::Edit => {
+                let focused_pane = self.focused_pane();
+                let selected = focused_pane.listbox.get_selected_str();
+                if !selected.is_empty() && !selected.ends_with('/') {
+                    if let Err(err) =
+                        external::edit(engine, focused_pane.device_files.as_mut(), &selected)
+                    {
+                        modal(engine, format!("Can not edit file: {}", err), vec!["OK"]);
+                    }
+                    focused_pane.update_filelist()?;
+                }
+            }
+            Action::Launch => {
+                let focused_pane = self.focused_pane();
+                let selected = focused_pane.listbox.get_selected_str();
+                if !selected.is_empty() && !selected.ends_with('/') {
+                    if let Err(err) =
+                        external::launch(engine, focused_pane.device_files.as_mut(), &selected)
+                    {
+                        modal(engine, format!("Can not open file: {}", err), vec!["OK"]);
+                    }
+                }
+            }
+            Action::Help => help(engine),
+            Action::Palette => {
+                if let Some(action) = palette(engine) {
+                    self.run_action(engine, action)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
 fn main_inner(engine: &mut ConsoleEngine) -> Result<()> {
-    // UI SETUP
-    let (cols, rows) = (engine.get_width() as u16, engine.get_height() as u16);
-    let mut main_layout = LayoutEngine::new();
-    let (left_idx, right_idx) = resize_layout(&mut main_layout, cols, rows);
-    let left_l = main_layout.get_layout(left_idx).unwrap();
-    let right_l = main_layout.get_layout(right_idx).unwrap();
+    let keymap = &config::get().keymap;
 
     // FILE LIST SETUP
     let mut adb = Adb::new()?;
+    let bookmarks = match Bookmarks::load() {
+        Ok(bookmarks) => bookmarks,
+        Err(err) => {
+            log::warn!("{:#}", err);
+            modal(
+                engine,
+                format!("{:#}\nStarting with empty bookmarks", err),
+                vec!["OK"],
+            );
+            Bookmarks::default()
+        }
+    };
     let local = Local::new()?;
     adb.populate_devices()?;
 
@@ -83,56 +585,76 @@ fn main_inner(engine: &mut ConsoleEngine) -> Result<()> {
     }
 
     let device = adb.devices[0].clone();
+    check_authorized(&device)?;
+
+    // UI SETUP
+    let (cols, rows) = (engine.get_width() as u16, engine.get_height() as u16);
+    let mut main_layout = LayoutEngine::new();
+    let (left_idx, right_idx) = resize_layout(&mut main_layout, cols, rows);
+    let left_l = main_layout.get_layout(left_idx).unwrap();
+    let right_l = main_layout.get_layout(right_idx).unwrap();
+
     // SETTING PANES
-    let mut device_pane = DeviceFilelist::new(&left_l, device)?;
-    let mut local_pane = DeviceFilelist::new(&right_l, local)?;
-    let mut bottom_bar = bottom_bar::StateBar::new(&engine);
+    let mut left_pane = DeviceFilelist::new(left_l, Box::new(device))?;
+    let right_pane = DeviceFilelist::new(right_l, Box::new(local))?;
+    let mut bottom_bar = bottom_bar::StateBar::new(engine);
+    let mut hint_bar = HintBar::new(engine);
 
-    device_pane.listbox.focused = true;
+    left_pane.listbox.focused = true;
+    let mut app = App {
+        adb,
+        bookmarks,
+        left_pane,
+        right_pane,
+        tar_mode: false,
+        dragging: None,
+        preview: None,
+    };
 
     loop {
         engine.wait_frame(); // wait for next frame + capture inputs
         engine.clear_screen(); // reset the screen
         engine.check_resize();
 
-        engine.print_screen(
-            device_pane.listbox.get_position().0,
-            device_pane.listbox.get_position().1,
-            device_pane.listbox.draw(),
-        );
-        engine.print_screen(
-            local_pane.listbox.get_position().0,
-            local_pane.listbox.get_position().1,
-            local_pane.listbox.draw(),
-        );
+        app.draw(engine);
 
         engine.print_screen(0, bottom_bar.y as i32, bottom_bar.draw());
 
-        device_pane.handle_listbox(&engine)?;
-        local_pane.handle_listbox(&engine)?;
+        let hints = app.hints();
+        hint_bar.set_hints(hints.iter().map(|(_, hint)| hint.clone()).collect());
+        engine.print_screen(0, hint_bar.y as i32, hint_bar.draw());
 
-        if engine.is_key_pressed(KeyCode::F(5)) && local_pane.listbox.focused {
-            device_pane.device_files.transfer_file(format!(
-                "{}/{}",
-                local_pane.device_files.get_working_directory(),
-                local_pane.listbox.get_selected_str()
-            ))?;
-            device_pane.update_filelist()?;
+        let mut actions = keymap.pressed_actions(engine);
+        if let Some(idx) = hint_bar.clicked(engine) {
+            actions.push(hints[idx].0);
         }
-
-        if device_pane.listbox.focused {
-            bottom_bar.set_text(device_pane.device_files.get_working_directory());
-        } else {
-            bottom_bar.set_text(local_pane.device_files.get_working_directory());
+        for action in actions {
+            app.run(engine, action);
         }
+        app.handle_mouse(engine);
 
-        engine.draw();
+        for pane in [&mut app.left_pane, &mut app.right_pane] {
+            if let Some((key, directory)) = pane.take_visit() {
+                app.bookmarks.visit(&key, &directory);
+            }
+        }
 
-        if engine.is_key_held(KeyCode::Esc) {
-            engine.clear_screen();
-            std::process::exit(0);
+        let tar_mode = app.tar_mode;
+        match app.dragging.clone() {
+            Some(name) => bottom_bar.set_text(format!("Drop on other pane to copy {}", name)),
+            None => {
+                let focused_pane = app.focused_pane();
+                bottom_bar.set_text(format!(
+                    "{}{}: {}",
+                    if tar_mode { "[tar] " } else { "" },
+                    focused_pane.device_files.get_source_name(),
+                    focused_pane.device_files.get_working_directory()
+                ));
+            }
         }
 
+        engine.draw();
+
         if let Some((w, h)) = engine.get_resize() {
             log::info!("Resized to {}x{}", w, h);
             let mut main_layout = LayoutEngine::new();
@@ -142,9 +664,10 @@ fn main_inner(engine: &mut ConsoleEngine) -> Result<()> {
             let left_l = main_layout.get_layout(left_idx).unwrap();
             let right_l = main_layout.get_layout(right_idx).unwrap();
 
-            device_pane.listbox.resize(left_l);
-            local_pane.listbox.resize(right_l);
+            app.left_pane.listbox.resize(left_l);
+            app.right_pane.listbox.resize(right_l);
             bottom_bar.resize(w, h);
+            hint_bar.resize(w, h);
 
             log::info!("Layout: {:#?}", left_l);
         }
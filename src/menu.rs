@@ -0,0 +1,140 @@
+use console_engine::{pixel, rect_style, screen::Screen, ConsoleEngine, KeyCode, MouseButton};
+
+use crate::{
+    config,
+    hint_bar::{draw_popup_hints, Hint},
+    layout::Layout,
+    listbox::{Click, ListBox},
+};
+
+fn recompute_sizes(ew: u32, eh: u32, title: &str, items: &[String]) -> Layout {
+    let content_w = items
+        .iter()
+        .map(|item| item.chars().count())
+        .chain(std::iter::once(title.chars().count()))
+        .max()
+        .unwrap_or(0) as u32;
+    // Preferred minimum gives way to terminal size, so tiny terminal does not panic
+    let w = (content_w + 4).max(20).min(ew);
+    let h = (items.len() as u32 + 2).max(5).min(eh * 2 / 3);
+
+    Layout {
+        x: (ew as i32 - w as i32) / 2,
+        y: (eh as i32 - h as i32) / 3,
+        w: w as i32,
+        h: h as i32,
+    }
+}
+
+fn inner_layout(layout: &Layout) -> Layout {
+    Layout {
+        x: 1,
+        y: 1,
+        w: (layout.w - 2).max(0),
+        h: (layout.h - 2).max(0),
+    }
+}
+
+/// Popup state passed to frame handler of `popup`
+pub struct PopupFrame<'a> {
+    /// Popup contents, cleared and framed with border and title
+    pub screen: &'a mut Screen,
+    /// Position and size of popup on terminal
+    pub layout: &'a Layout,
+    /// Whether popup was resized since previous frame
+    pub resized: bool,
+    /// Index of clicked popup hint
+    pub clicked: Option<usize>,
+}
+
+/// Runs popup window loop: draws `hints` in hint bar, frames popup with border and `title` and
+/// lets `frame` handle input and draw contents, until it returns value closing the popup.
+/// Popup is placed by `recompute_sizes` from terminal width and height
+pub fn popup<T>(
+    engine: &mut ConsoleEngine,
+    title: &str,
+    hints: &[Hint],
+    recompute_sizes: impl Fn(u32, u32) -> Layout,
+    mut frame: impl FnMut(&mut ConsoleEngine, PopupFrame) -> Option<T>,
+) -> T {
+    let mut layout = recompute_sizes(engine.get_width(), engine.get_height());
+    let mut screen = Screen::new_fill(layout.w as u32, layout.h as u32, pixel::pxl(' '));
+    let mut resized = false;
+
+    loop {
+        engine.wait_frame(); // wait for next frame + capture inputs
+        engine.clear_screen(); // reset the screen
+        engine.check_resize();
+        let clicked = draw_popup_hints(engine, hints);
+
+        screen.clear();
+        screen.rect_border(
+            0,
+            0,
+            layout.w - 1,
+            layout.h - 1,
+            rect_style::BorderStyle::new_light(),
+        );
+        screen.print(2, 0, title);
+
+        let popup_frame = PopupFrame {
+            screen: &mut screen,
+            layout: &layout,
+            resized,
+            clicked,
+        };
+        if let Some(result) = frame(engine, popup_frame) {
+            return result;
+        }
+
+        engine.print_screen(layout.x, layout.y, &screen);
+        engine.draw();
+
+        resized = false;
+        if let Some((w, h)) = engine.get_resize() {
+            layout = recompute_sizes(w as u32, h as u32);
+            screen.resize(layout.w as u32, layout.h as u32);
+            resized = true;
+        }
+    }
+}
+
+/// Shows popup list and waits until user selects an item with Enter or cancels with Esc.
+/// Returns index of selected item, so items with same label are told apart
+pub fn menu(engine: &mut ConsoleEngine, title: &str, items: Vec<String>) -> Option<usize> {
+    let sizes = |w, h| recompute_sizes(w, h, title, &items);
+    let layout = sizes(engine.get_width(), engine.get_height());
+    let mut listbox = ListBox::new(&inner_layout(&layout), true);
+    listbox.set_content(items.clone());
+    let hints = [Hint::new("Enter", "Select"), Hint::new("Esc", "Cancel")];
+
+    popup(engine, title, &hints, sizes, |engine, frame| {
+        if frame.resized {
+            listbox.resize(&inner_layout(frame.layout));
+        }
+
+        if engine.is_key_pressed(KeyCode::Esc) || frame.clicked == Some(1) {
+            return Some(None);
+        }
+
+        let activated = engine
+            .get_mouse_press(MouseButton::Left)
+            .and_then(|(x, y)| listbox.click(x as i32 - frame.layout.x, y as i32 - frame.layout.y))
+            == Some(Click::Activate);
+
+        if (engine.is_key_pressed(KeyCode::Enter) || frame.clicked == Some(0) || activated)
+            && !items.is_empty()
+        {
+            return Some(Some(listbox.get_selected_idx()));
+        }
+
+        for action in config::get().keymap.pressed_actions(engine) {
+            listbox.handle_action(action);
+        }
+        listbox.handle_wheel(engine);
+
+        let (x, y) = listbox.get_position();
+        frame.screen.print_screen(x, y, listbox.draw());
+        None
+    })
+}
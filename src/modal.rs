@@ -1,24 +1,73 @@
-use console_engine::{pixel, rect_style, screen::Screen, Color, ConsoleEngine, KeyCode};
+use std::time::Duration;
+
+use console_engine::{
+    crossterm::event::{self, Event, KeyEvent, MouseEvent, MouseEventKind},
+    pixel, rect_style,
+    screen::Screen,
+    Color, ConsoleEngine, KeyCode, MouseButton,
+};
+
+use crate::{
+    file_operations::format_size,
+    hint_bar::{draw_popup_hints, Hint, HintBar},
+};
+
+/// Splits buttons into rows that fit into `width` columns. Each button takes its label width plus
+/// one column for the number hint before it
+fn layout_buttons(buttons: &[&str], width: u32) -> Vec<Vec<(usize, i32)>> {
+    let mut rows = vec![Vec::new()];
+    let mut coord_x = 2;
 
-fn draw_buttons(screen: &mut Screen, buttons: Vec<&str>, selected: usize) {
     for (idx, button) in buttons.iter().enumerate() {
-        let coord_x = idx as i32 * (button.chars().count() + 1) as i32 + 2;
+        let button_w = button.chars().count() as i32 + 2;
+        if coord_x + button_w > width as i32 && !rows.last().unwrap().is_empty() {
+            rows.push(Vec::new());
+            coord_x = 2;
+        }
+        rows.last_mut().unwrap().push((idx, coord_x));
+        coord_x += button_w;
+    }
+
+    rows
+}
+
+/// Gets position of each button on modal screen: index, x and y
+fn button_positions(screen: &Screen, buttons: &[&str]) -> Vec<(usize, i32, i32)> {
+    let rows = layout_buttons(buttons, screen.get_width());
+    let first_row = screen.get_height() as i32 - 1 - rows.len() as i32;
+
+    rows.iter()
+        .enumerate()
+        .flat_map(|(row_idx, row)| {
+            row.iter()
+                .map(move |&(idx, coord_x)| (idx, coord_x, first_row + row_idx as i32))
+        })
+        .collect()
+}
+
+/// Gets index of button at `x`, `y` on modal screen, including its number hint
+fn button_at(screen: &Screen, buttons: &[&str], x: i32, y: i32) -> Option<usize> {
+    button_positions(screen, buttons)
+        .into_iter()
+        .find(|&(idx, coord_x, coord_y)| {
+            let end = coord_x + buttons[idx].chars().count() as i32;
+            coord_y == y && (coord_x - 1..end).contains(&x)
+        })
+        .map(|(idx, _, _)| idx)
+}
 
+fn draw_buttons(screen: &mut Screen, buttons: &[&str], selected: usize) {
+    for (idx, coord_x, coord_y) in button_positions(screen, buttons) {
+        let button = buttons[idx];
         if idx == selected {
-            screen.print_fbg(
-                coord_x,
-                screen.get_height() as i32 - 2,
-                button,
-                Color::Black,
-                Color::White,
-            );
+            screen.print_fbg(coord_x, coord_y, button, Color::Black, Color::White);
         } else {
-            screen.print(coord_x, screen.get_height() as i32 - 2, button);
+            screen.print(coord_x, coord_y, button);
         }
 
         screen.print_fbg(
             coord_x - 1,
-            screen.get_height() as i32 - 2,
+            coord_y,
             (idx + 1).to_string().as_str(),
             Color::White,
             Color::DarkBlue,
@@ -26,24 +75,32 @@ fn draw_buttons(screen: &mut Screen, buttons: Vec<&str>, selected: usize) {
     }
 }
 
-fn recompute_sizes(ew: u32, eh: u32, count: u32) -> (i32, i32, u32, u32) {
-    let w = (count + 2).clamp(10, ew);
-    let h = (eh / 4).clamp(5, eh as u32);
-    let mut x = 0;
-    let y = eh as i32 / 4;
+/// Computes modal position and size from the terminal size and modal contents
+fn recompute_sizes(ew: u32, eh: u32, message: &str, buttons: &[&str]) -> (i32, i32, u32, u32) {
+    let text_w = message
+        .lines()
+        .map(|line| line.chars().count() as u32)
+        .max()
+        .unwrap_or(0);
+    let buttons_w = buttons
+        .iter()
+        .map(|button| button.chars().count() as u32 + 2)
+        .sum::<u32>()
+        + 2;
+    let w = (text_w.max(buttons_w) + 2).clamp(10, ew);
 
-    if w < ew {
-        x = (ew as i32 - count as i32) / 2;
-    }
+    let text = textwrap::fill(message, w as usize - 2);
+    let content_h = text.lines().count() as u32 + layout_buttons(buttons, w).len() as u32 + 3;
+    let h = (eh / 4).max(content_h).clamp(5, eh);
+
+    let x = (ew as i32 - w as i32) / 2;
+    let y = ((eh as i32 - h as i32) / 2).min(eh as i32 / 4);
 
     (x, y, w, h)
 }
 
-pub fn modal(engine: &mut ConsoleEngine, message: String, buttons: Vec<&str>) -> String {
-    let (_, _, w, h) = recompute_sizes(engine.get_width(), engine.get_height(), message.chars().count() as u32);
-    let mut screen = Screen::new_fill(w, h, pixel::pxl(' '));
-    let mut selected = 0;
-
+fn redraw(screen: &mut Screen, message: &str, buttons: &[&str], selected: usize) {
+    screen.clear();
     screen.rect_border(
         0,
         0,
@@ -51,22 +108,52 @@ pub fn modal(engine: &mut ConsoleEngine, message: String, buttons: Vec<&str>) ->
         screen.get_height() as i32 - 1,
         rect_style::BorderStyle::new_light(),
     );
-    screen.print(1, 1, textwrap::fill(&message, w as usize).as_str());
-    draw_buttons(&mut screen, buttons.clone(), selected);
+    screen.print(
+        1,
+        1,
+        textwrap::fill(message, screen.get_width() as usize - 2).as_str(),
+    );
+    draw_buttons(screen, buttons, selected);
+}
+
+pub fn modal(engine: &mut ConsoleEngine, message: String, buttons: Vec<&str>) -> String {
+    let (_, _, w, h) = recompute_sizes(engine.get_width(), engine.get_height(), &message, &buttons);
+    let mut screen = Screen::new_fill(w, h, pixel::pxl(' '));
+    let mut selected = 0;
+
+    let hints = [
+        Hint::new("Enter", "Select"),
+        Hint::info("Left/Right", "Choose"),
+        Hint::info(format!("1-{}", buttons.len()), "Button"),
+    ];
+
+    redraw(&mut screen, &message, &buttons, selected);
 
     loop {
         engine.wait_frame(); // wait for next frame + capture inputs
         engine.clear_screen(); // reset the screen
         engine.check_resize();
-        draw_buttons(&mut screen, buttons.clone(), selected);
-        let (x, y, _, _) = recompute_sizes(engine.get_width(), engine.get_height(), message.chars().count() as u32);
+        draw_buttons(&mut screen, &buttons, selected);
+        let (x, y, _, _) =
+            recompute_sizes(engine.get_width(), engine.get_height(), &message, &buttons);
+        let clicked = draw_popup_hints(engine, &hints);
         engine.print_screen(x, y, &screen);
         engine.draw();
 
-        if engine.is_key_pressed(KeyCode::Enter) {
+        if engine.is_key_pressed(KeyCode::Enter) || clicked == Some(0) {
             return buttons[selected].to_string();
         }
 
+        if let Some(idx) =
+            engine
+                .get_mouse_press(MouseButton::Left)
+                .and_then(|(mouse_x, mouse_y)| {
+                    button_at(&screen, &buttons, mouse_x as i32 - x, mouse_y as i32 - y)
+                })
+        {
+            return buttons[idx].to_string();
+        }
+
         if engine.is_key_pressed(KeyCode::Left) {
             selected = selected.saturating_sub(1);
         }
@@ -75,27 +162,95 @@ pub fn modal(engine: &mut ConsoleEngine, message: String, buttons: Vec<&str>) ->
             selected = (selected + 1).clamp(0, buttons.len() - 1);
         }
 
-        for i in 0..buttons.len() {
+        for (i, button) in buttons.iter().enumerate() {
             if engine.is_key_pressed(KeyCode::Char(
                 char::from_digit(i as u32 + 1, 10).unwrap_or('1'),
             )) {
-                return buttons[i].to_string();
+                return button.to_string();
             }
         }
 
         if let Some((new_w, new_h)) = engine.get_resize() {
-            let (_, _, w, h) = recompute_sizes(new_w as u32, new_h as u32, message.chars().count() as u32);
-            screen.resize(w as u32, h as u32);
-            screen.clear();
-            screen.rect_border(
-                0,
-                0,
-                screen.get_width() as i32 - 1,
-                screen.get_height() as i32 - 1,
-                rect_style::BorderStyle::new_light(),
-            );
-            screen.print(1, 1, textwrap::fill(&message, w as usize).as_str());
-            draw_buttons(&mut screen, buttons.clone(), selected);
+            let (_, _, w, h) = recompute_sizes(new_w as u32, new_h as u32, &message, &buttons);
+            screen.resize(w, h);
+            redraw(&mut screen, &message, &buttons, selected);
+        }
+    }
+}
+
+/// Shows error of failed action, after which application keeps running
+pub fn error(engine: &mut ConsoleEngine, err: &anyhow::Error) {
+    log::error!("{:#}", err);
+    modal(engine, format!("Error: {}", err), vec!["OK"]);
+}
+
+/// Checks whether Esc was pressed or cancel hint of progress window was clicked since last check.
+/// Reads terminal events directly instead of waiting for next frame, so transfers are not slowed
+/// down to frame rate
+pub fn progress_cancelled(engine: &ConsoleEngine) -> bool {
+    let mut bar = HintBar::new(engine);
+    bar.set_hints(progress_hints());
+    bar.draw();
+
+    let mut cancelled = false;
+    while event::poll(Duration::ZERO).unwrap_or(false) {
+        match event::read() {
+            Ok(Event::Key(KeyEvent {
+                code: KeyCode::Esc, ..
+            })) => cancelled = true,
+            Ok(Event::Mouse(MouseEvent {
+                kind: MouseEventKind::Down(MouseButton::Left),
+                column,
+                row,
+                ..
+            })) => cancelled |= bar.hint_at(column as u32, row as u32) == Some(0),
+            _ => {}
         }
     }
+    cancelled
+}
+
+fn progress_hints() -> Vec<Hint> {
+    vec![Hint::new("Esc", "Cancel")]
+}
+
+/// Draws transfer progress window with `done` of `total` bytes completed. Without `total` only
+/// transferred amount is shown
+pub fn progress(engine: &mut ConsoleEngine, message: &str, done: u64, total: Option<u64>) {
+    let w = (engine.get_width() / 2).max(20).min(engine.get_width());
+    let mut screen = Screen::new_fill(w, 5, pixel::pxl(' '));
+    let bar_w = w as usize - 4;
+    let filled = match total {
+        Some(total) => (bar_w as u64 * done.min(total))
+            .checked_div(total)
+            .unwrap_or(bar_w as u64) as usize,
+        None => 0,
+    };
+
+    screen.rect_border(0, 0, w as i32 - 1, 4, rect_style::BorderStyle::new_light());
+    screen.print(2, 1, message);
+    screen.print_fbg(2, 2, &" ".repeat(filled), Color::Reset, Color::White);
+    screen.print_fbg(
+        2 + filled as i32,
+        2,
+        &" ".repeat(bar_w - filled),
+        Color::Reset,
+        Color::DarkGrey,
+    );
+    match total {
+        Some(total) => screen.print(
+            2,
+            3,
+            &format!("{} / {}", format_size(done), format_size(total)),
+        ),
+        None => screen.print(2, 3, &format_size(done)),
+    }
+
+    engine.print_screen(
+        (engine.get_width() - w) as i32 / 2,
+        engine.get_height() as i32 / 3,
+        &screen,
+    );
+    draw_popup_hints(engine, &progress_hints());
+    engine.draw();
 }
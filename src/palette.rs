@@ -0,0 +1,146 @@
+use console_engine::{ConsoleEngine, KeyCode, MouseButton};
+
+use crate::{
+    config,
+    hint_bar::Hint,
+    keymap::Action,
+    layout::Layout,
+    listbox::{Click, ListBox},
+    menu::popup,
+    text_input::TextInput,
+};
+
+/// Matches `query` characters in order inside `text`, ignoring case.
+/// Consecutive matches and matches at word starts score higher.
+/// Returns `None` if `text` does not contain all characters of `query`
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text = text.to_lowercase().chars().collect::<Vec<char>>();
+    let mut score = 0;
+    let mut pos = 0;
+    let mut previous_match = None;
+
+    for char in query.to_lowercase().chars().filter(|char| *char != ' ') {
+        let found = pos + text[pos..].iter().position(|&other| other == char)?;
+
+        score += 1;
+        if previous_match.is_some_and(|previous| previous + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+
+        previous_match = Some(found);
+        pos = found + 1;
+    }
+
+    Some(score)
+}
+
+/// Gets palette line of `action`: description and its keys
+fn action_line(action: Action) -> String {
+    let keys = config::get()
+        .keymap
+        .get_keys(action)
+        .iter()
+        .map(|key| key.to_string())
+        .collect::<Vec<String>>()
+        .join(", ");
+    format!("{:<48} {}", action.description(), keys)
+}
+
+fn recompute_sizes(ew: u32, eh: u32) -> Layout {
+    let w = 72.min(ew);
+    let h = (Action::ALL.len() as u32 + 4).max(6).min(eh * 2 / 3);
+
+    Layout {
+        x: (ew as i32 - w as i32) / 2,
+        y: (eh as i32 - h as i32) / 3,
+        w: w as i32,
+        h: h as i32,
+    }
+}
+
+fn list_layout(layout: &Layout) -> Layout {
+    Layout {
+        x: 1,
+        y: 2,
+        w: (layout.w - 2).max(0),
+        h: (layout.h - 3).max(0),
+    }
+}
+
+/// Shows list of all actions with their keys, filtered by typed query.
+/// Returns action chosen with Enter or `None` if user cancelled with Esc
+pub fn palette(engine: &mut ConsoleEngine) -> Option<Action> {
+    let keymap = &config::get().keymap;
+    let layout = recompute_sizes(engine.get_width(), engine.get_height());
+    let mut listbox = ListBox::new(&list_layout(&layout), true);
+    let mut query = TextInput::new();
+    let mut actions = Vec::new();
+    let mut filter = true;
+    let hints = [
+        Hint::new("Enter", "Run"),
+        Hint::new("Esc", "Cancel"),
+        Hint::info("Type", "Filter"),
+    ];
+
+    popup(
+        engine,
+        "Command palette",
+        &hints,
+        recompute_sizes,
+        |engine, frame| {
+            if frame.resized {
+                listbox.resize(&list_layout(frame.layout));
+            }
+
+            if engine.is_key_pressed(KeyCode::Esc) || frame.clicked == Some(1) {
+                return Some(None);
+            }
+
+            let activated = engine
+                .get_mouse_press(MouseButton::Left)
+                .and_then(|(x, y)| {
+                    listbox.click(x as i32 - frame.layout.x, y as i32 - frame.layout.y)
+                })
+                == Some(Click::Activate);
+
+            if (engine.is_key_pressed(KeyCode::Enter) || frame.clicked == Some(0) || activated)
+                && !actions.is_empty()
+            {
+                return Some(actions.get(listbox.get_selected_idx()).copied());
+            }
+
+            for action in keymap.pressed_actions(engine) {
+                listbox.handle_action(action);
+            }
+            listbox.handle_wheel(engine);
+            filter |= query.handle_events(engine);
+
+            if filter {
+                let mut scored = Action::ALL
+                    .iter()
+                    .copied()
+                    .filter(|&action| action != Action::Palette)
+                    .filter_map(|action| {
+                        let text = format!("{} {}", action.description(), action.name());
+                        fuzzy_score(&query.value, &text).map(|score| (score, action))
+                    })
+                    .collect::<Vec<(i32, Action)>>();
+                // Stable sort keeps declaration order for equal scores
+                scored.sort_by_key(|(score, _)| -score);
+
+                actions = scored.into_iter().map(|(_, action)| action).collect();
+                listbox.set_content(actions.iter().map(|&action| action_line(action)).collect());
+                filter = false;
+            }
+
+            frame.screen.print(2, 1, ">");
+            query.draw(frame.screen, 4, 1, (frame.layout.w - 6).max(0) as u32);
+            let (x, y) = listbox.get_position();
+            frame.screen.print_screen(x, y, listbox.draw());
+            None
+        },
+    )
+}
@@ -0,0 +1,289 @@
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use console_engine::{pixel, screen::Screen, Color, ConsoleEngine};
+
+use crate::{
+    config, external,
+    file_operations::FileOperations,
+    layout::Layout,
+    thumbnail::{self, Image},
+};
+
+/// Bytes of file read for preview
+const PREVIEW_BYTES: u64 = 16 * 1024;
+
+/// Cursor has to rest on entry this long before it is loaded, so scrolling through
+/// device directory does not run adb for every entry passed
+const LOAD_DELAY: Duration = Duration::from_millis(150);
+
+/// Largest image that is pulled and decoded for preview
+const MAX_IMAGE_BYTES: u64 = 32 * 1024 * 1024;
+
+/// Loaded preview contents
+enum Content {
+    Empty,
+    Message(String),
+    Text(String),
+    Binary(Vec<u8>),
+    Image(Image),
+}
+
+/// Checks whether `data` looks like text: valid UTF-8 without NUL bytes.
+/// Character cut at the end of data is allowed
+pub fn is_text(data: &[u8]) -> bool {
+    if data.contains(&0) {
+        return false;
+    }
+    match std::str::from_utf8(data) {
+        Ok(_) => true,
+        Err(err) => err.error_len().is_none() && data.len() - err.valid_up_to() < 4,
+    }
+}
+
+/// Formats `data` as hex dump lines with offset, bytes and printable characters,
+/// fitting into `width` columns
+pub fn hex_dump(data: &[u8], width: usize) -> Vec<String> {
+    // Offset column takes 10 columns, each byte 3 for hex and 1 for character
+    let per_line = ((width.saturating_sub(12) / 4) / 4 * 4).clamp(4, 16);
+
+    data.chunks(per_line)
+        .enumerate()
+        .map(|(idx, chunk)| {
+            let hex = chunk
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<Vec<String>>()
+                .join(" ");
+            let chars = chunk
+                .iter()
+                .map(|&byte| {
+                    if byte.is_ascii_graphic() || byte == b' ' {
+                        byte as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect::<String>();
+            format!(
+                "{:08x}  {:<hex_w$}  {}",
+                idx * per_line,
+                hex,
+                chars,
+                hex_w = per_line * 3 - 1
+            )
+        })
+        .collect()
+}
+
+/// Quick view of file selected in active pane, drawn in place of inactive pane
+pub struct Preview {
+    screen: Screen,
+    x: i32,
+    y: i32,
+    /// Source name, working directory and entry name of loaded preview
+    loaded: Option<(String, String, String)>,
+    /// Entry waiting for cursor to rest on it, with time it was selected
+    pending: Option<((String, String, String), Instant)>,
+    title: String,
+    content: Content,
+    /// Content lines wrapped for `lines_width` columns. Zero width means they need rewrapping
+    lines: Vec<String>,
+    /// Colors of image cells rendered along with `lines`
+    cells: Vec<Vec<(Color, Color)>>,
+    lines_width: usize,
+}
+
+impl Preview {
+    pub fn new(layout: &Layout) -> Self {
+        Self {
+            screen: Screen::new_fill(layout.w as u32, layout.h as u32, pixel::pxl(' ')),
+            x: layout.x,
+            y: layout.y,
+            loaded: None,
+            pending: None,
+            title: String::new(),
+            content: Content::Empty,
+            lines: Vec::new(),
+            cells: Vec::new(),
+            lines_width: 0,
+        }
+    }
+
+    /// Loads preview of entry `name` of `files` working directory once cursor rests on it
+    pub fn update(
+        &mut self,
+        engine: &mut ConsoleEngine,
+        files: &mut dyn FileOperations,
+        name: &str,
+    ) {
+        let key = (
+            files.get_source_name(),
+            files.get_working_directory().to_string(),
+            name.to_string(),
+        );
+        if self.loaded.as_ref() == Some(&key) {
+            return;
+        }
+
+        match &self.pending {
+            Some((pending, since)) if *pending == key => {
+                if since.elapsed() < LOAD_DELAY {
+                    return;
+                }
+            }
+            _ => {
+                self.pending = Some((key, Instant::now()));
+                return;
+            }
+        }
+
+        self.pending = None;
+        self.load(engine, files, name);
+        self.loaded = Some(key);
+    }
+
+    fn load(&mut self, engine: &mut ConsoleEngine, files: &mut dyn FileOperations, name: &str) {
+        self.lines_width = 0;
+        self.title = name.to_string();
+        self.content = if name.is_empty() {
+            Content::Empty
+        } else if name.ends_with('/') {
+            Content::Message("Directory".to_string())
+        } else {
+            let info = files.file_info(name).unwrap_or_else(|err| {
+                log::warn!("{}", err);
+                None
+            });
+            if let Some(info) = info {
+                self.title = format!(
+                    "{}  {}  {}",
+                    name,
+                    info.format_size(),
+                    info.format_modified()
+                );
+            }
+
+            if thumbnail::is_supported(name) {
+                match Self::load_image(engine, files, name, info.map(|info| info.size)) {
+                    Ok(image) => {
+                        self.title += &format!("  {}x{}", image.original.0, image.original.1);
+                        Content::Image(image)
+                    }
+                    Err(err) => Content::Message(format!("Can not show image: {}", err)),
+                }
+            } else {
+                match files.read_range(name, 0, PREVIEW_BYTES) {
+                    Ok(data) if is_text(&data) => {
+                        Content::Text(String::from_utf8_lossy(&data).replace('\t', "    "))
+                    }
+                    Ok(data) => Content::Binary(data),
+                    Err(err) => Content::Message(format!("Can not read file: {}", err)),
+                }
+            }
+        };
+    }
+
+    /// Decodes image file. Files of other sources are pulled to cache first, showing progress
+    /// window that can be cancelled
+    fn load_image(
+        engine: &mut ConsoleEngine,
+        files: &mut dyn FileOperations,
+        name: &str,
+        size: Option<u64>,
+    ) -> Result<Image> {
+        match size {
+            Some(size) if size <= MAX_IMAGE_BYTES => {}
+            Some(_) => return Err(anyhow!("image is too large")),
+            None => return Err(anyhow!("image size is unknown")),
+        }
+        let path = external::local_copy(engine, files, name)?
+            .ok_or_else(|| anyhow!("pulling was cancelled"))?;
+        thumbnail::decode(&std::fs::read(path)?)
+    }
+
+    /// Moves and resizes preview to `layout`, if it changed
+    pub fn set_layout(&mut self, layout: &Layout) {
+        if (self.screen.get_width(), self.screen.get_height()) != (layout.w as u32, layout.h as u32)
+        {
+            self.screen.resize(layout.w as u32, layout.h as u32);
+            self.lines_width = 0;
+        }
+        self.x = layout.x;
+        self.y = layout.y;
+    }
+
+    /// Wraps content lines to `width` columns
+    fn wrap_lines(&self, width: usize) -> Vec<String> {
+        let wrap = |text: &str| {
+            textwrap::wrap(text, width.max(1))
+                .into_iter()
+                .map(|line| line.to_string())
+                .collect::<Vec<String>>()
+        };
+
+        match &self.content {
+            Content::Empty => Vec::new(),
+            Content::Message(message) => wrap(message),
+            Content::Text(text) => text
+                .lines()
+                .flat_map(|line| {
+                    if line.is_empty() {
+                        vec![String::new()]
+                    } else {
+                        wrap(line)
+                    }
+                })
+                .collect(),
+            Content::Binary(data) => hex_dump(data, width),
+            Content::Image(_) => Vec::new(),
+        }
+    }
+
+    pub fn get_position(&self) -> (i32, i32) {
+        (self.x, self.y)
+    }
+
+    pub fn draw(&mut self) -> &Screen {
+        let theme = &config::get().theme;
+        let width = self.screen.get_width() as usize;
+        let height = self.screen.get_height() as usize;
+
+        if self.lines_width != width {
+            self.lines = self.wrap_lines(width);
+            self.cells = match &self.content {
+                Content::Image(image) => image.render(width, height.saturating_sub(1)),
+                _ => Vec::new(),
+            };
+            self.lines_width = width;
+        }
+
+        self.screen.clear();
+        let title = self.title.chars().take(width).collect::<String>();
+        self.screen.print_fbg(
+            0,
+            0,
+            &format!("{:<width$}", title, width = width),
+            theme.list_cursor_fg,
+            theme.list_cursor_bg,
+        );
+        for (row, line) in self.lines.iter().take(height.saturating_sub(1)).enumerate() {
+            let line = line.chars().take(width).collect::<String>();
+            self.screen
+                .print_fbg(0, row as i32 + 1, &line, theme.list_fg, Color::Reset);
+        }
+        // Image is centered horizontally, each cell shows two pixels with upper half block
+        for (row, cells) in self.cells.iter().enumerate() {
+            let left = width.saturating_sub(cells.len()) / 2;
+            for (column, &(top, bottom)) in cells.iter().enumerate() {
+                self.screen.set_pxl(
+                    (left + column) as i32,
+                    row as i32 + 1,
+                    pixel::pxl_fbg('▀', top, bottom),
+                );
+            }
+        }
+
+        &self.screen
+    }
+}
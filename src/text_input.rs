@@ -0,0 +1,52 @@
+use console_engine::{screen::Screen, Color, ConsoleEngine, KeyCode, KeyModifiers};
+
+/// Single line text field. console_engine has no text input events,
+/// so printable ASCII keys are polled every frame
+#[derive(Clone, Default)]
+pub struct TextInput {
+    pub value: String,
+}
+
+impl TextInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends typed characters and handles Backspace. Returns `true` if value changed
+    pub fn handle_events(&mut self, engine: &ConsoleEngine) -> bool {
+        let mut changed = false;
+
+        for char in ' '..='~' {
+            if engine.is_key_pressed(KeyCode::Char(char))
+                || engine.is_key_pressed_with_modifier(KeyCode::Char(char), KeyModifiers::SHIFT)
+            {
+                self.value.push(char);
+                changed = true;
+            }
+        }
+
+        if engine.is_key_pressed(KeyCode::Backspace) && self.value.pop().is_some() {
+            changed = true;
+        }
+
+        changed
+    }
+
+    /// Draws value with cursor at `x`, `y`, showing its end if it does not fit into `w` columns
+    pub fn draw(&self, screen: &mut Screen, x: i32, y: i32, w: u32) {
+        let visible = self
+            .value
+            .chars()
+            .skip((self.value.chars().count() + 1).saturating_sub(w as usize))
+            .collect::<String>();
+
+        screen.print(x, y, &visible);
+        screen.print_fbg(
+            x + visible.chars().count() as i32,
+            y,
+            " ",
+            Color::Reset,
+            Color::White,
+        );
+    }
+}
@@ -0,0 +1,253 @@
+use std::env;
+
+use anyhow::{anyhow, Result};
+use console_engine::Color;
+
+/// Decoded images are shrunk to fit this many pixels in each direction, which is enough for
+/// any preview pane and keeps rendering cheap
+const MAX_SIZE: usize = 256;
+
+/// Largest PNG image that is decoded. Whole frame is decoded before shrinking, so this limits
+/// memory taken by decoding
+const MAX_PNG_PIXELS: u64 = 32 * 1024 * 1024;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const JPEG_SIGNATURE: &[u8] = b"\xff\xd8\xff";
+
+/// Decoded RGB image
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    /// Size of original image
+    pub original: (usize, usize),
+    pixels: Vec<[u8; 3]>,
+}
+
+/// Checks whether file `name` can be decoded, by its extension
+pub fn is_supported(name: &str) -> bool {
+    let name = name.to_lowercase();
+    [".png", ".jpg", ".jpeg"]
+        .iter()
+        .any(|extension| name.ends_with(extension))
+}
+
+/// Decodes PNG or JPEG image, shrinking it to fit `MAX_SIZE`
+pub fn decode(data: &[u8]) -> Result<Image> {
+    let image = if data.starts_with(PNG_SIGNATURE) {
+        decode_png(data)?
+    } else if data.starts_with(JPEG_SIGNATURE) {
+        decode_jpeg(data)?
+    } else {
+        return Err(anyhow!("not a PNG or JPEG image"));
+    };
+
+    let (width, height) = fit(image.width, image.height, MAX_SIZE, MAX_SIZE);
+    Ok(image.resize(width, height))
+}
+
+fn decode_png(data: &[u8]) -> Result<Image> {
+    let mut decoder = png::Decoder::new(data);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let (width, height) = (reader.info().width, reader.info().height);
+    if width as u64 * height as u64 > MAX_PNG_PIXELS {
+        return Err(anyhow!("{}x{} image is too large", width, height));
+    }
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer)?;
+
+    let channels = info.color_type.samples();
+    let pixels = buffer[..info.buffer_size()]
+        .chunks_exact(channels)
+        .map(|pixel| match pixel {
+            [luma] => [*luma; 3],
+            [luma, alpha] => [blend(*luma, *alpha); 3],
+            [r, g, b] => [*r, *g, *b],
+            [r, g, b, alpha] => [blend(*r, *alpha), blend(*g, *alpha), blend(*b, *alpha)],
+            _ => [0; 3],
+        })
+        .collect();
+
+    let (width, height) = (info.width as usize, info.height as usize);
+    Ok(Image {
+        width,
+        height,
+        original: (width, height),
+        pixels,
+    })
+}
+
+fn decode_jpeg(data: &[u8]) -> Result<Image> {
+    let mut decoder = jpeg_decoder::Decoder::new(data);
+    decoder.read_info()?;
+    let original = decoder
+        .info()
+        .map(|info| (info.width as usize, info.height as usize))
+        .unwrap_or_default();
+    // Decoder skips detail not needed for requested size, which is much faster for photos
+    decoder.scale(MAX_SIZE as u16, MAX_SIZE as u16)?;
+    let data = decoder.decode()?;
+    let info = decoder
+        .info()
+        .ok_or_else(|| anyhow!("JPEG image has no frame"))?;
+
+    let pixels = match info.pixel_format {
+        jpeg_decoder::PixelFormat::L8 => data.iter().map(|&luma| [luma; 3]).collect(),
+        jpeg_decoder::PixelFormat::L16 => data.chunks_exact(2).map(|luma| [luma[0]; 3]).collect(),
+        jpeg_decoder::PixelFormat::RGB24 => data
+            .chunks_exact(3)
+            .map(|pixel| [pixel[0], pixel[1], pixel[2]])
+            .collect(),
+        jpeg_decoder::PixelFormat::CMYK32 => data
+            .chunks_exact(4)
+            .map(|pixel| {
+                let k = 255 - pixel[3] as u32;
+                let channel = |value: u8| ((255 - value as u32) * k / 255) as u8;
+                [channel(pixel[0]), channel(pixel[1]), channel(pixel[2])]
+            })
+            .collect(),
+    };
+
+    Ok(Image {
+        width: info.width as usize,
+        height: info.height as usize,
+        original,
+        pixels,
+    })
+}
+
+/// Blends channel value over black background
+fn blend(value: u8, alpha: u8) -> u8 {
+    (value as u32 * alpha as u32 / 255) as u8
+}
+
+/// Computes size of `width`x`height` image scaled down to fit `max_width`x`max_height`,
+/// keeping aspect ratio
+fn fit(width: usize, height: usize, max_width: usize, max_height: usize) -> (usize, usize) {
+    if width <= max_width && height <= max_height {
+        return (width, height);
+    }
+    if width * max_height > height * max_width {
+        (max_width, (height * max_width / width).max(1))
+    } else {
+        ((width * max_height / height).max(1), max_height)
+    }
+}
+
+impl Image {
+    /// Resizes image, averaging source pixels covered by each new pixel
+    fn resize(&self, width: usize, height: usize) -> Image {
+        if (width, height) == (self.width, self.height) {
+            return Image {
+                pixels: self.pixels.clone(),
+                ..*self
+            };
+        }
+
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let (y0, y1) = span(y, height, self.height);
+            for x in 0..width {
+                let (x0, x1) = span(x, width, self.width);
+                let mut sum = [0u32; 3];
+                for row in y0..y1 {
+                    for pixel in &self.pixels[row * self.width + x0..row * self.width + x1] {
+                        for (total, &channel) in sum.iter_mut().zip(pixel) {
+                            *total += channel as u32;
+                        }
+                    }
+                }
+                let count = ((y1 - y0) * (x1 - x0)) as u32;
+                pixels.push(sum.map(|total| (total / count) as u8));
+            }
+        }
+
+        Image {
+            width,
+            height,
+            original: self.original,
+            pixels,
+        }
+    }
+
+    /// Renders image for `columns`x`rows` terminal cells, two pixels per cell drawn with upper
+    /// half block. Returns foreground and background colors of each cell, rows are as wide as
+    /// scaled image
+    pub fn render(&self, columns: usize, rows: usize) -> Vec<Vec<(Color, Color)>> {
+        if columns == 0 || rows == 0 || self.width == 0 || self.height == 0 {
+            return Vec::new();
+        }
+        let (width, height) = fit(self.width, self.height, columns, rows * 2);
+        let scaled = self.resize(width, height);
+        let truecolor = env::var("COLORTERM")
+            .map(|colorterm| colorterm.contains("truecolor") || colorterm.contains("24bit"))
+            .unwrap_or(false);
+        let color = |pixel: [u8; 3]| {
+            if truecolor {
+                Color::Rgb {
+                    r: pixel[0],
+                    g: pixel[1],
+                    b: pixel[2],
+                }
+            } else {
+                Color::AnsiValue(ansi_256(pixel))
+            }
+        };
+
+        (0..height)
+            .step_by(2)
+            .map(|y| {
+                (0..width)
+                    .map(|x| {
+                        let top = scaled.pixels[y * width + x];
+                        let bottom = if y + 1 < height {
+                            color(scaled.pixels[(y + 1) * width + x])
+                        } else {
+                            Color::Reset
+                        };
+                        (color(top), bottom)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Gets range of source pixels covered by pixel `idx` of `size` pixels, scaled from `source_size`
+fn span(idx: usize, size: usize, source_size: usize) -> (usize, usize) {
+    let start = idx * source_size / size;
+    let end = ((idx + 1) * source_size / size).max(start + 1);
+    (start, end.min(source_size))
+}
+
+/// Gets closest color of xterm 256 color palette: 6x6x6 color cube or grayscale ramp
+fn ansi_256(pixel: [u8; 3]) -> u8 {
+    let cube_level = |value: u8| -> u8 {
+        match value {
+            0..=47 => 0,
+            48..=114 => 1,
+            _ => (value - 35) / 40,
+        }
+    };
+    let cube_value = |level: u8| if level == 0 { 0 } else { 55 + level * 40 };
+    let distance = |a: [u8; 3], b: [u8; 3]| {
+        a.iter()
+            .zip(&b)
+            .map(|(&a, &b)| (a as i32 - b as i32).pow(2))
+            .sum::<i32>()
+    };
+
+    let levels = pixel.map(cube_level);
+    let cube = levels.map(cube_value);
+    let cube_idx = 16 + 36 * levels[0] + 6 * levels[1] + levels[2];
+
+    let average = pixel.iter().map(|&value| value as u32).sum::<u32>() / 3;
+    let gray_level = (average.saturating_sub(3) / 10).min(23) as u8;
+    let gray = 8 + gray_level * 10;
+
+    if distance(pixel, [gray; 3]) < distance(pixel, cube) {
+        232 + gray_level
+    } else {
+        cube_idx
+    }
+}
@@ -0,0 +1,343 @@
+use std::{
+    fs::{self, File},
+    io::{Read, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use console_engine::ConsoleEngine;
+
+use crate::{
+    conflict::ConflictResolver,
+    file_operations::FileOperations,
+    modal::{self, modal},
+};
+
+const CHUNK_SIZE: usize = 64 * 1024;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Gets temporary name for file that is being transferred: `dir/.name.adbexplorer-part`
+pub fn part_name(name: &str) -> String {
+    match name.rfind('/') {
+        Some(pos) => format!("{}.{}.adbexplorer-part", &name[..pos + 1], &name[pos + 1..]),
+        None => format!(".{}.adbexplorer-part", name),
+    }
+}
+
+/// Transfer progress window. Throttles redraws and checks whether user cancelled transfer with Esc
+/// or by clicking its hint
+struct Progress<'a> {
+    engine: &'a mut ConsoleEngine,
+    message: String,
+    total: Option<u64>,
+    last_update: Instant,
+}
+
+impl<'a> Progress<'a> {
+    fn new(engine: &'a mut ConsoleEngine, message: String, total: Option<u64>) -> Self {
+        modal::progress(engine, &message, 0, total);
+        Self {
+            engine,
+            message,
+            total,
+            last_update: Instant::now(),
+        }
+    }
+
+    /// Updates progress window. Returns `false` if transfer was cancelled
+    fn update(&mut self, done: u64) -> bool {
+        if self.last_update.elapsed() < PROGRESS_INTERVAL {
+            return true;
+        }
+        self.last_update = Instant::now();
+
+        if modal::progress_cancelled(self.engine) {
+            return false;
+        }
+        modal::progress(self.engine, &self.message, done, self.total);
+        true
+    }
+}
+
+/// Moves data from `input` to `output` until end of input, counting from `done` bytes.
+/// Returns total bytes transferred, or `None` if transfer was cancelled
+fn pump<R: Read + ?Sized, W: Write + ?Sized>(
+    progress: &mut Progress,
+    input: &mut R,
+    output: &mut W,
+    mut done: u64,
+) -> Result<Option<u64>> {
+    let mut buffer = vec![0; CHUNK_SIZE];
+    loop {
+        let count = input.read(&mut buffer)?;
+        if count == 0 {
+            return Ok(Some(done));
+        }
+        output.write_all(&buffer[..count])?;
+        done += count as u64;
+
+        if !progress.update(done) {
+            return Ok(None);
+        }
+    }
+}
+
+/// Copies file or directory `name` from source working directory into destination working directory,
+/// asking user what to do with existing files
+pub fn copy(
+    engine: &mut ConsoleEngine,
+    source: &mut dyn FileOperations,
+    destination: &mut dyn FileOperations,
+    name: &str,
+) -> Result<()> {
+    let files = source.walk(name)?;
+    let mut resolver = ConflictResolver::new(files.len() > 1);
+
+    for file in files {
+        let info = match source.file_info(&file)? {
+            Some(info) => info,
+            None => continue,
+        };
+
+        let destination_name = match resolver.resolve(engine, &file, info, destination)? {
+            Some(destination_name) => destination_name,
+            None => {
+                log::info!("Skipping {}", file);
+                continue;
+            }
+        };
+
+        if !copy_file(
+            engine,
+            source,
+            &file,
+            destination,
+            &destination_name,
+            info.size,
+        )? {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Copies single file. Data is written to temporary part file which is renamed to `destination_name`
+/// on completion. If part file is left from previous failed attempt, only remaining bytes are
+/// transferred. Returns `false` if transfer was cancelled
+fn copy_file(
+    engine: &mut ConsoleEngine,
+    source: &mut dyn FileOperations,
+    file: &str,
+    destination: &mut dyn FileOperations,
+    destination_name: &str,
+    size: u64,
+) -> Result<bool> {
+    let part = part_name(destination_name);
+    let offset = destination
+        .file_info(&part)?
+        .map(|info| info.size)
+        .filter(|&partial| partial > 0 && partial <= size)
+        .unwrap_or(0);
+
+    if offset > 0 {
+        log::info!("Resuming {} from {} bytes", file, offset);
+    }
+
+    let mut input = source.open_read(file, offset)?;
+    let mut output = destination.open_write(&part, offset > 0)?;
+    let mut progress = Progress::new(engine, format!("Copying {}", file), Some(size));
+
+    let done = match pump(&mut progress, &mut input, &mut output, offset)? {
+        Some(done) => done,
+        None => {
+            log::info!("Copying of {} cancelled", file);
+            input.cancel()?;
+            output.cancel()?;
+            destination.remove(&part)?;
+            return Ok(false);
+        }
+    };
+
+    input.finish()?;
+    output.finish()?;
+
+    if done != size {
+        return Err(anyhow!(
+            "Transfer of {} stopped at {} of {} bytes. Copy it again to resume",
+            file,
+            done,
+            size
+        ));
+    }
+
+    destination.rename(&part, destination_name)?;
+    Ok(true)
+}
+
+/// Copies file `name` of `source` working directory to local `path`.
+/// Returns `false` if transfer was cancelled
+pub fn pull(
+    engine: &mut ConsoleEngine,
+    source: &mut dyn FileOperations,
+    name: &str,
+    path: &Path,
+) -> Result<bool> {
+    let size = source
+        .file_info(name)?
+        .ok_or_else(|| anyhow!("{} does not exist", name))?
+        .size;
+    let mut input = source.open_read(name, 0)?;
+    let mut output = File::create(path)?;
+    let mut progress = Progress::new(engine, format!("Pulling {}", name), Some(size));
+
+    let done = match pump(&mut progress, &mut input, &mut output, 0)? {
+        Some(done) => done,
+        None => {
+            log::info!("Pulling of {} cancelled", name);
+            input.cancel()?;
+            return Ok(false);
+        }
+    };
+    // Incomplete copy must not be mistaken for the file, like in cache
+    let result = input.finish().and_then(|_| {
+        if done != size {
+            return Err(anyhow!(
+                "Pulling of {} stopped at {} of {} bytes",
+                name,
+                done,
+                size
+            ));
+        }
+        Ok(true)
+    });
+    if result.is_err() {
+        fs::remove_file(path)?;
+    }
+    result
+}
+
+/// Writes local file `path` over file `name` of `destination` working directory. File is uploaded
+/// under temporary name first, so cancelled or failed upload leaves existing file intact. Then its
+/// contents are copied over existing file, which keeps its permissions and owner.
+/// Returns `false` if transfer was cancelled
+pub fn push(
+    engine: &mut ConsoleEngine,
+    path: &Path,
+    destination: &mut dyn FileOperations,
+    name: &str,
+) -> Result<bool> {
+    let mut input = File::open(path)?;
+    let size = input.metadata()?.len();
+    let part = part_name(name);
+    let mut output = destination.open_write(&part, false)?;
+    let mut progress = Progress::new(engine, format!("Pushing {}", name), Some(size));
+
+    let result = match pump(&mut progress, &mut input, &mut output, 0) {
+        Ok(Some(_)) => output
+            .finish()
+            .and_then(|_| destination.overwrite(&part, name))
+            .map(|_| true),
+        Ok(None) => {
+            log::info!("Pushing of {} cancelled", name);
+            output.cancel().map(|_| false)
+        }
+        Err(err) => {
+            let _ = output.cancel();
+            Err(err)
+        }
+    };
+
+    match result {
+        Ok(pushed) => {
+            destination.remove(&part)?;
+            Ok(pushed)
+        }
+        Err(err) => {
+            let _ = destination.remove(&part);
+            Err(err)
+        }
+    }
+}
+
+/// Copies directory `name` from source working directory into destination working directory as
+/// single tar stream, which is much faster than copying many small files one by one.
+/// Existing files are overwritten. Reports files that tar failed to extract
+pub fn copy_tar(
+    engine: &mut ConsoleEngine,
+    source: &mut dyn FileOperations,
+    destination: &mut dyn FileOperations,
+    name: &str,
+) -> Result<()> {
+    let name = name.trim_end_matches('/');
+    if destination.file_info(name)?.is_some() {
+        let button = modal(
+            engine,
+            format!(
+                "{} already exists in destination\nExisting files will be overwritten",
+                name
+            ),
+            vec!["Continue", "Cancel"],
+        );
+        if button != "Continue" {
+            return Ok(());
+        }
+    }
+
+    let mut input = source.open_tar_read(name)?;
+    let mut output = match destination.open_tar_write() {
+        Ok(output) => output,
+        Err(err) => {
+            let _ = input.cancel();
+            return Err(err);
+        }
+    };
+    let mut progress = Progress::new(engine, format!("Streaming {}", name), None);
+
+    match pump(&mut progress, &mut input, &mut output, 0) {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            log::info!("Streaming of {} cancelled", name);
+            input.cancel()?;
+            output.cancel()?;
+            return Ok(());
+        }
+        // Both sides are stopped, so neither tar keeps running after failed stream
+        Err(err) => {
+            let _ = input.cancel();
+            let _ = output.cancel();
+            return Err(err);
+        }
+    }
+
+    let sent = input.finish()?;
+    let extracted = output.finish()?;
+    let failed = sent
+        .iter()
+        .filter(|file| !extracted.contains(file))
+        .collect::<Vec<&String>>();
+
+    for file in &failed {
+        log::error!("{} was not extracted", file);
+    }
+
+    let mut message = format!(
+        "Copied {} of {} entries from {}",
+        sent.len() - failed.len(),
+        sent.len(),
+        name
+    );
+    if !failed.is_empty() {
+        message.push_str("\nFailed:");
+        for file in failed.iter().take(10) {
+            message.push_str(&format!("\n{}", file));
+        }
+        if failed.len() > 10 {
+            message.push_str(&format!("\nand {} more, see log", failed.len() - 10));
+        }
+    }
+    modal(engine, message, vec!["OK"]);
+
+    Ok(())
+}
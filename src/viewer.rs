@@ -0,0 +1,768 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use console_engine::{pixel, screen::Screen, Color, ConsoleEngine, KeyCode, KeyModifiers};
+
+use crate::{
+    config,
+    file_operations::{FileOperations, FileReader},
+    hint_bar::{draw_popup_hints, Hint},
+    keymap::Action,
+    text_input::TextInput,
+};
+
+/// Size of file chunks read for display
+const CHUNK_SIZE: u64 = 64 * 1024;
+
+/// Chunks kept in memory, least recently used are dropped first
+const MAX_CHUNKS: usize = 32;
+
+/// Bytes indexed per frame, so indexing large file does not freeze the viewer
+const INDEX_STEP: usize = 256 * 1024;
+
+/// Only this many first bytes of each line are shown and searched
+const MAX_LINE_BYTES: u64 = 4096;
+
+/// How often file size is checked in follow mode
+const FOLLOW_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Time spent searching or indexing for a jump per frame, so working through large file can be
+/// cancelled with Esc
+const SEARCH_STEP: Duration = Duration::from_millis(50);
+
+/// Text typed at the bottom line
+enum Prompt {
+    Search { forward: bool },
+    GotoLine,
+}
+
+/// Line index and chunk cache of viewed file. Only line start offsets and a few chunks are kept
+/// in memory, so multi-megabyte files can be viewed
+struct ViewedFile<'a> {
+    files: &'a mut dyn FileOperations,
+    path: String,
+    /// File size, `None` if not known until whole file is indexed
+    size: Option<u64>,
+    /// Offset of each line start
+    line_starts: Vec<u64>,
+    /// Bytes scanned for line starts
+    indexed: u64,
+    /// Stream continuing from `indexed` offset
+    indexer: Option<Box<dyn FileReader>>,
+    chunks: HashMap<u64, Vec<u8>>,
+    /// Chunk indices from least to most recently used
+    chunk_order: VecDeque<u64>,
+}
+
+impl<'a> ViewedFile<'a> {
+    fn new(files: &'a mut dyn FileOperations, path: &str) -> Result<Self> {
+        let size = files.file_info(path)?.map(|info| info.size);
+        Ok(Self {
+            files,
+            path: path.to_string(),
+            size,
+            line_starts: vec![0],
+            indexed: 0,
+            indexer: None,
+            chunks: HashMap::new(),
+            chunk_order: VecDeque::new(),
+        })
+    }
+
+    fn is_indexed(&self) -> bool {
+        self.indexer.is_none() && self.size == Some(self.indexed)
+    }
+
+    /// Gets number of lines indexed so far. Newline at the end of file does not start a new line
+    fn line_count(&self) -> usize {
+        match self.line_starts.last() {
+            Some(&start) if start == self.indexed => self.line_starts.len() - 1,
+            _ => self.line_starts.len(),
+        }
+    }
+
+    /// Scans up to `budget` more bytes for line starts
+    fn index_step(&mut self, budget: usize) -> Result<()> {
+        if self.is_indexed() {
+            return Ok(());
+        }
+        if self.indexer.is_none() {
+            self.indexer = Some(self.files.open_read(&self.path, self.indexed)?);
+        }
+
+        let reader = self.indexer.as_mut().unwrap();
+        let mut buffer = vec![0; 16 * 1024];
+        let mut read_total = 0;
+        while read_total < budget {
+            let read = reader.read(&mut buffer)?;
+            if read == 0 {
+                self.indexer.take().unwrap().finish()?;
+                self.size = Some(self.indexed);
+                break;
+            }
+            for (idx, &byte) in buffer[..read].iter().enumerate() {
+                if byte == b'\n' {
+                    self.line_starts.push(self.indexed + idx as u64 + 1);
+                }
+            }
+            self.indexed += read as u64;
+            read_total += read;
+        }
+        Ok(())
+    }
+
+    /// Indexes file until it has more than `line` lines or is indexed completely
+    fn index_until(&mut self, line: usize) -> Result<()> {
+        while self.line_count() <= line && !self.is_indexed() {
+            self.index_step(INDEX_STEP)?;
+        }
+        Ok(())
+    }
+
+    /// Checks whether file grew or was truncated, for follow mode
+    fn refresh_size(&mut self) {
+        let size = match self.files.file_info(&self.path) {
+            Ok(Some(info)) => info.size,
+            _ => return,
+        };
+        if size < self.indexed {
+            // File was truncated or replaced, index it again
+            if let Some(indexer) = self.indexer.take() {
+                let _ = indexer.cancel();
+            }
+            self.line_starts = vec![0];
+            self.indexed = 0;
+            self.chunks.clear();
+            self.chunk_order.clear();
+        }
+        self.size = Some(size);
+    }
+
+    fn chunk(&mut self, idx: u64) -> Result<&[u8]> {
+        // Chunk read before file grew may miss its end
+        let stale = self.chunks.get(&idx).is_some_and(|chunk| {
+            (chunk.len() as u64) < CHUNK_SIZE
+                && idx * CHUNK_SIZE + (chunk.len() as u64) < self.indexed
+        });
+        if stale || !self.chunks.contains_key(&idx) {
+            let data = self
+                .files
+                .read_range(&self.path, idx * CHUNK_SIZE, CHUNK_SIZE)?;
+            self.chunks.insert(idx, data);
+        }
+
+        self.chunk_order.retain(|&other| other != idx);
+        self.chunk_order.push_back(idx);
+        if self.chunk_order.len() > MAX_CHUNKS {
+            if let Some(oldest) = self.chunk_order.pop_front() {
+                self.chunks.remove(&oldest);
+            }
+        }
+        Ok(&self.chunks[&idx])
+    }
+
+    fn read_bytes(&mut self, start: u64, end: u64) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        let mut offset = start;
+        while offset < end {
+            let chunk_idx = offset / CHUNK_SIZE;
+            let chunk_start = chunk_idx * CHUNK_SIZE;
+            let chunk = self.chunk(chunk_idx)?;
+            let from = (offset - chunk_start) as usize;
+            let to = ((end - chunk_start) as usize).min(chunk.len());
+            if from >= to {
+                break;
+            }
+            data.extend_from_slice(&chunk[from..to]);
+            offset = chunk_start + to as u64;
+        }
+        Ok(data)
+    }
+
+    /// Gets text of line `idx`, limited to `MAX_LINE_BYTES`
+    fn line(&mut self, idx: usize) -> Result<String> {
+        let start = self.line_starts[idx];
+        let end = match self.line_starts.get(idx + 1) {
+            Some(&next) => next - 1,
+            None => self.indexed,
+        };
+        let data = self.read_bytes(start, end.min(start + MAX_LINE_BYTES))?;
+        Ok(String::from_utf8_lossy(&data)
+            .trim_end_matches('\r')
+            .replace('\t', "    "))
+    }
+}
+
+/// Finds `query` in `line`. Query without upper case letters matches any case.
+/// Returns char range of match. Chars are compared one by one, since lowercasing whole line may
+/// change its length and shift the range
+fn find_match(line: &str, query: &str) -> Option<(usize, usize)> {
+    if query.is_empty() {
+        return None;
+    }
+    let line = line.chars().collect::<Vec<char>>();
+    let query = query.chars().collect::<Vec<char>>();
+    let ignore_case = !query.iter().any(|char| char.is_uppercase());
+    let same = |a: char, b: char| a == b || ignore_case && a.to_lowercase().eq(b.to_lowercase());
+
+    (0..=line.len().checked_sub(query.len())?)
+        .find(|&start| {
+            line[start..start + query.len()]
+                .iter()
+                .zip(&query)
+                .all(|(&a, &b)| same(a, b))
+        })
+        .map(|start| (start, start + query.len()))
+}
+
+/// Search running across frames
+struct Search {
+    forward: bool,
+    /// Next line to check
+    line: usize,
+}
+
+/// Full-screen viewer of text file
+struct Viewer<'a> {
+    file: ViewedFile<'a>,
+    /// First visible line
+    top: usize,
+    /// First visible column
+    left: usize,
+    follow: bool,
+    last_poll: Instant,
+    query: String,
+    /// Line of last search match, searching again starts after it
+    found: Option<usize>,
+    searching: Option<Search>,
+    /// Line that is scrolled to once file is indexed up to it, `usize::MAX` for the end
+    jumping: Option<usize>,
+    prompt: Option<(Prompt, TextInput)>,
+    message: String,
+}
+
+impl<'a> Viewer<'a> {
+    /// Gets number of text lines shown below status line
+    fn page_height(screen: &Screen) -> usize {
+        (screen.get_height() as usize).saturating_sub(1).max(1)
+    }
+
+    fn scroll_to(&mut self, line: usize, page_h: usize) {
+        let max_top = self.file.line_count().saturating_sub(page_h);
+        self.top = line.min(max_top);
+    }
+
+    /// Starts search, which runs in `search_step` of following frames
+    fn search(&mut self, forward: bool) {
+        if self.query.is_empty() {
+            return;
+        }
+
+        // Search continues next to last match, or starts at first visible line
+        let line = match self.found {
+            Some(found) if forward => found + 1,
+            Some(0) => return self.not_found(),
+            Some(found) => found - 1,
+            None => self.top,
+        };
+        self.searching = Some(Search { forward, line });
+    }
+
+    /// Checks lines for search match until `SEARCH_STEP` time runs out
+    fn search_step(&mut self, page_h: usize) -> Result<()> {
+        let Search { forward, mut line } = match self.searching.take() {
+            Some(search) => search,
+            None => return Ok(()),
+        };
+        let started = Instant::now();
+
+        loop {
+            self.file.index_until(line)?;
+            if line >= self.file.line_count() {
+                break;
+            }
+            if find_match(&self.file.line(line)?, &self.query).is_some() {
+                self.found = Some(line);
+                self.message.clear();
+                if line < self.top || line >= self.top + page_h {
+                    self.scroll_to(line.saturating_sub(page_h / 2), page_h);
+                }
+                return Ok(());
+            }
+
+            if forward {
+                line += 1;
+            } else if line == 0 {
+                break;
+            } else {
+                line -= 1;
+            }
+
+            if started.elapsed() >= SEARCH_STEP {
+                self.message = format!(
+                    "Searching \"{}\" at line {}, Esc to stop",
+                    self.query,
+                    line + 1
+                );
+                self.searching = Some(Search { forward, line });
+                return Ok(());
+            }
+        }
+        self.not_found();
+        Ok(())
+    }
+
+    /// Indexes file until `SEARCH_STEP` time runs out, then scrolls to jump line once it is indexed
+    fn jump_step(&mut self, page_h: usize) -> Result<()> {
+        let line = match self.jumping {
+            Some(line) => line,
+            None => return Ok(()),
+        };
+        let started = Instant::now();
+
+        while self.file.line_count() <= line && !self.file.is_indexed() {
+            if started.elapsed() >= SEARCH_STEP {
+                self.message = if line == usize::MAX {
+                    "Indexing to the end, Esc to stop".to_string()
+                } else {
+                    format!("Indexing to line {}, Esc to stop", line + 1)
+                };
+                return Ok(());
+            }
+            self.file.index_step(INDEX_STEP)?;
+        }
+
+        self.jumping = None;
+        self.message.clear();
+        if line != usize::MAX && line >= self.file.line_count() {
+            self.message = format!("File has {} lines", self.file.line_count());
+        }
+        self.scroll_to(line, page_h);
+        Ok(())
+    }
+
+    fn not_found(&mut self) {
+        self.message = format!("\"{}\" not found", self.query);
+    }
+
+    /// Handles text typed into prompt and runs it on Enter
+    fn handle_prompt(&mut self, engine: &ConsoleEngine) {
+        let (prompt, input) = match &mut self.prompt {
+            Some(prompt) => prompt,
+            None => return,
+        };
+
+        if engine.is_key_pressed(KeyCode::Esc) {
+            self.prompt = None;
+            return;
+        }
+        if !engine.is_key_pressed(KeyCode::Enter) {
+            input.handle_events(engine);
+            return;
+        }
+
+        let value = input.value.clone();
+        match prompt {
+            Prompt::Search { forward } => {
+                let forward = *forward;
+                self.prompt = None;
+                if !value.is_empty() {
+                    self.query = value;
+                }
+                self.found = None;
+                self.search(forward);
+            }
+            Prompt::GotoLine => {
+                self.prompt = None;
+                match value.trim().parse::<usize>() {
+                    Ok(line) if line > 0 => {
+                        self.follow = false;
+                        self.jumping = Some(line - 1);
+                    }
+                    _ => self.message = format!("Invalid line number \"{}\"", value),
+                }
+            }
+        }
+    }
+
+    /// Handles keys and hint bar clicks while prompt is closed
+    fn handle_keys(
+        &mut self,
+        engine: &ConsoleEngine,
+        clicked: Option<usize>,
+        page_h: usize,
+    ) -> Result<()> {
+        let keymap = &config::get().keymap;
+        let char_pressed = |char| engine.is_key_pressed(KeyCode::Char(char));
+        let shift_pressed =
+            |char| engine.is_key_pressed_with_modifier(KeyCode::Char(char), KeyModifiers::SHIFT);
+
+        let mut top = self.top;
+        let mut moved = false;
+        for action in keymap.pressed_actions(engine) {
+            moved = true;
+            match action {
+                Action::CursorDown => top += 1,
+                Action::CursorUp => top = top.saturating_sub(1),
+                Action::PageDown => top += page_h,
+                Action::PageUp => top = top.saturating_sub(page_h),
+                Action::Home => top = 0,
+                Action::End => self.jumping = Some(usize::MAX),
+                _ => moved = false,
+            }
+        }
+        if engine.is_mouse_scrolled_down() {
+            top += 3;
+            moved = true;
+        }
+        if engine.is_mouse_scrolled_up() {
+            top = top.saturating_sub(3);
+            moved = true;
+        }
+        if moved {
+            self.follow = false;
+            self.found = None;
+            self.message.clear();
+            self.file.index_until(top + page_h)?;
+            self.scroll_to(top, page_h);
+        }
+
+        if engine.is_key_pressed(KeyCode::Right) {
+            self.left += 8;
+        }
+        if engine.is_key_pressed(KeyCode::Left) {
+            self.left = self.left.saturating_sub(8);
+        }
+
+        if char_pressed('/') || clicked == Some(1) {
+            self.prompt = Some((Prompt::Search { forward: true }, TextInput::new()));
+        } else if char_pressed('?') || shift_pressed('?') || clicked == Some(2) {
+            self.prompt = Some((Prompt::Search { forward: false }, TextInput::new()));
+        } else if char_pressed(':') || shift_pressed(':') || char_pressed('g') || clicked == Some(5)
+        {
+            self.prompt = Some((Prompt::GotoLine, TextInput::new()));
+        } else if char_pressed('n') || clicked == Some(3) {
+            self.search(true);
+        } else if shift_pressed('N') || char_pressed('N') || clicked == Some(4) {
+            self.search(false);
+        } else if char_pressed('f') || clicked == Some(6) {
+            self.follow = !self.follow;
+            self.last_poll = Instant::now() - FOLLOW_INTERVAL;
+        }
+        Ok(())
+    }
+
+    fn draw(&mut self, screen: &mut Screen) -> Result<()> {
+        let theme = &config::get().theme;
+        let width = screen.get_width() as usize;
+        let page_h = Self::page_height(screen);
+
+        screen.clear();
+        let progress = match self.file.size {
+            Some(size) if !self.file.is_indexed() && size > 0 => {
+                format!("  indexing {}%", self.file.indexed * 100 / size)
+            }
+            _ => String::new(),
+        };
+        let status = format!(
+            "{}  line {}/{}{}{}  {}",
+            self.file.path,
+            (self.top + 1).min(self.file.line_count()),
+            self.file.line_count(),
+            progress,
+            if self.follow { "  [follow]" } else { "" },
+            self.message
+        );
+        screen.print_fbg(
+            0,
+            0,
+            &format!("{:<width$}", status, width = width)
+                .chars()
+                .take(width)
+                .collect::<String>(),
+            theme.status_bar_fg,
+            theme.status_bar_bg,
+        );
+
+        let number_w = (self.top + page_h).to_string().len();
+        let text_w = width.saturating_sub(number_w + 1);
+        let last = (self.top + page_h).min(self.file.line_count());
+        for (row, idx) in (self.top..last).enumerate() {
+            let line = self.file.line(idx)?;
+            let visible = line
+                .chars()
+                .skip(self.left)
+                .take(text_w)
+                .collect::<String>();
+            let y = row as i32 + 1;
+            screen.print_fbg(
+                0,
+                y,
+                &format!("{:>w$}", idx + 1, w = number_w),
+                theme.list_inactive_fg,
+                Color::Reset,
+            );
+            screen.print_fbg(
+                number_w as i32 + 1,
+                y,
+                &visible,
+                theme.list_fg,
+                Color::Reset,
+            );
+
+            if let Some((start, end)) = find_match(&line, &self.query) {
+                let start = start.max(self.left);
+                let end = end.min(self.left + text_w);
+                if start < end {
+                    let matched = line
+                        .chars()
+                        .skip(start)
+                        .take(end - start)
+                        .collect::<String>();
+                    screen.print_fbg(
+                        (number_w + 1 + start - self.left) as i32,
+                        y,
+                        &matched,
+                        theme.list_cursor_fg,
+                        theme.list_cursor_bg,
+                    );
+                }
+            }
+        }
+
+        if let Some((prompt, input)) = &self.prompt {
+            let label = match prompt {
+                Prompt::Search { forward: true } => "Search: ",
+                Prompt::Search { forward: false } => "Search backward: ",
+                Prompt::GotoLine => "Go to line: ",
+            };
+            let y = screen.get_height() as i32 - 1;
+            screen.print(0, y, label);
+            input.draw(
+                screen,
+                label.len() as i32,
+                y,
+                (width.saturating_sub(label.len())) as u32,
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Shows file `path` of `files` working directory in full-screen viewer until user closes it
+pub fn view(engine: &mut ConsoleEngine, files: &mut dyn FileOperations, path: &str) -> Result<()> {
+    let keymap = &config::get().keymap;
+    // Bottom line of terminal is left for hints
+    let mut screen = Screen::new_fill(engine.get_width(), engine.get_height() - 1, pixel::pxl(' '));
+    let mut viewer = Viewer {
+        file: ViewedFile::new(files, path)?,
+        top: 0,
+        left: 0,
+        follow: false,
+        last_poll: Instant::now(),
+        query: String::new(),
+        found: None,
+        searching: None,
+        jumping: None,
+        prompt: None,
+        message: String::new(),
+    };
+    let hints = [
+        Hint::new("Esc", "Close"),
+        Hint::new("/", "Search"),
+        Hint::new("?", "Back"),
+        Hint::new("n", "Next"),
+        Hint::new("N", "Prev"),
+        Hint::new(":", "Line"),
+        Hint::new("f", "Follow"),
+    ];
+
+    loop {
+        engine.wait_frame(); // wait for next frame + capture inputs
+        engine.clear_screen(); // reset the screen
+        engine.check_resize();
+        let clicked = draw_popup_hints(engine, &hints);
+        let page_h = Viewer::page_height(&screen);
+
+        viewer.file.index_step(INDEX_STEP)?;
+        if viewer.follow {
+            if viewer.last_poll.elapsed() >= FOLLOW_INTERVAL {
+                viewer.last_poll = Instant::now();
+                viewer.file.refresh_size();
+            }
+            viewer.scroll_to(usize::MAX, page_h);
+        }
+
+        if viewer.searching.is_some() {
+            if engine.is_key_pressed(KeyCode::Esc) || clicked == Some(0) {
+                viewer.searching = None;
+                viewer.message = "Search stopped".to_string();
+            } else {
+                viewer.search_step(page_h)?;
+            }
+        } else if viewer.jumping.is_some() {
+            if engine.is_key_pressed(KeyCode::Esc) || clicked == Some(0) {
+                viewer.jumping = None;
+                viewer.message = "Indexing stopped".to_string();
+            } else {
+                viewer.jump_step(page_h)?;
+            }
+        } else if viewer.prompt.is_some() {
+            viewer.handle_prompt(engine);
+        } else {
+            if engine.is_key_pressed(KeyCode::Esc)
+                || engine.is_key_pressed(KeyCode::Char('q'))
+                || keymap.is_pressed(engine, Action::View)
+                || clicked == Some(0)
+            {
+                return Ok(());
+            }
+            viewer.handle_keys(engine, clicked, page_h)?;
+        }
+
+        viewer.draw(&mut screen)?;
+        engine.print_screen(0, 0, &screen);
+        engine.draw();
+
+        if let Some((w, h)) = engine.get_resize() {
+            screen.resize(w as u32, h as u32 - 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+    use crate::file_operations::Local;
+
+    /// Writes `contents` to temporary file and returns local file list of its directory
+    fn temp_file(name: &str, contents: &[u8]) -> (Local, String) {
+        let name = format!("adbexplorer-viewer-{}-{}", name, std::process::id());
+        let directory = env::temp_dir();
+        fs::write(directory.join(&name), contents).unwrap();
+
+        let mut local = Local::new().unwrap();
+        local.change_directory(&directory.to_string_lossy());
+        (local, name)
+    }
+
+    #[test]
+    fn find_match_ignores_case_of_lower_case_query() {
+        assert_eq!(find_match("Hello World", "world"), Some((6, 11)));
+        assert_eq!(find_match("Hello World", "World"), Some((6, 11)));
+        assert_eq!(find_match("Hello world", "World"), None);
+        assert_eq!(find_match("Hello", ""), None);
+        assert_eq!(find_match("Hi", "Hello"), None);
+    }
+
+    #[test]
+    fn find_match_counts_chars() {
+        assert_eq!(find_match("Grüße aus Köln", "köln"), Some((10, 14)));
+        assert_eq!(find_match("ÄÖÜ äöü", "äöü"), Some((0, 3)));
+        assert_eq!(find_match("日本語のテキスト", "テキ"), Some((4, 6)));
+        // Lower case of 'İ' is two chars long, which must not shift the range
+        assert_eq!(find_match("İstanbul abc", "abc"), Some((9, 12)));
+    }
+
+    #[test]
+    fn lines_across_chunks() {
+        let mut contents = vec![b'a'; CHUNK_SIZE as usize - 3];
+        contents.extend_from_slice("\nneedle über chunk\nlast".as_bytes());
+        let (mut local, name) = temp_file("chunks", &contents);
+        let mut file = ViewedFile::new(&mut local, &name).unwrap();
+
+        while !file.is_indexed() {
+            file.index_step(1000).unwrap();
+        }
+        assert_eq!(file.line_count(), 3);
+        assert_eq!(file.line_starts[1], CHUNK_SIZE - 2);
+        assert_eq!(file.line(1).unwrap(), "needle über chunk");
+        assert_eq!(file.line(2).unwrap(), "last");
+
+        fs::remove_file(env::temp_dir().join(&name)).unwrap();
+    }
+
+    #[test]
+    fn search_across_chunks() {
+        let mut contents = Vec::new();
+        for idx in 0..20_000 {
+            contents.extend_from_slice(format!("line {}\n", idx).as_bytes());
+        }
+        contents.extend_from_slice("Straße\n".as_bytes());
+        let (mut local, name) = temp_file("search", &contents);
+        let mut viewer = Viewer {
+            file: ViewedFile::new(&mut local, &name).unwrap(),
+            top: 0,
+            left: 0,
+            follow: false,
+            last_poll: Instant::now(),
+            query: "STRASSE".to_string(),
+            found: None,
+            searching: None,
+            jumping: None,
+            prompt: None,
+            message: String::new(),
+        };
+
+        viewer.search(true);
+        while viewer.searching.is_some() {
+            viewer.search_step(10).unwrap();
+        }
+        assert_eq!(viewer.found, None);
+
+        viewer.query = "straße".to_string();
+        viewer.search(true);
+        while viewer.searching.is_some() {
+            viewer.search_step(10).unwrap();
+        }
+        assert_eq!(viewer.found, Some(20_000));
+        assert_eq!(viewer.top, 20_001 - 10);
+
+        viewer.query = "line 1999".to_string();
+        viewer.search(false);
+        while viewer.searching.is_some() {
+            viewer.search_step(10).unwrap();
+        }
+        assert_eq!(viewer.found, Some(19_999));
+
+        drop(viewer);
+        fs::remove_file(env::temp_dir().join(&name)).unwrap();
+    }
+
+    #[test]
+    fn jump_to_end() {
+        let contents = "line\n".repeat(100_000);
+        let (mut local, name) = temp_file("jump", contents.as_bytes());
+        let mut viewer = Viewer {
+            file: ViewedFile::new(&mut local, &name).unwrap(),
+            top: 0,
+            left: 0,
+            follow: false,
+            last_poll: Instant::now(),
+            query: String::new(),
+            found: None,
+            searching: None,
+            jumping: Some(usize::MAX),
+            prompt: None,
+            message: String::new(),
+        };
+
+        while viewer.jumping.is_some() {
+            viewer.jump_step(10).unwrap();
+        }
+        assert_eq!(viewer.top, 100_000 - 10);
+        assert!(viewer.message.is_empty());
+
+        viewer.jumping = Some(200_000);
+        viewer.jump_step(10).unwrap();
+        assert_eq!(viewer.message, "File has 100000 lines");
+
+        drop(viewer);
+        fs::remove_file(env::temp_dir().join(&name)).unwrap();
+    }
+}